@@ -4,18 +4,28 @@ use futures::{FutureExt, SinkExt, StreamExt};
 
 use crate::{
     eprintln_driver::Eprintlned,
-    examples::Example,
+    example_id::ExampleId,
+    examples::{Document, Example},
     expression::driver::{EvaluateExpression, ExpressionEvent},
+    file_driver::{FileCommand, FileWritten},
+    nix_command::EvalMode,
+    println_driver::Printlned,
     repl::driver::{ReplCommand, ReplEvent},
+    report::ReportConfig,
 };
 
 use self::state::State;
 
 pub(crate) struct Inputs {
+    pub(crate) report_config: ReportConfig,
+    pub(crate) eval_mode: Option<EvalMode>,
+    pub(crate) documents: Vec<Document>,
     pub(crate) examples: Vec<Example>,
     pub(crate) repl_events: futures::stream::LocalBoxStream<'static, ReplEvent>,
     pub(crate) expression_events: futures::stream::LocalBoxStream<'static, ExpressionEvent>,
     pub(crate) eprintln_events: futures::stream::LocalBoxStream<'static, Eprintlned>,
+    pub(crate) println_events: futures::stream::LocalBoxStream<'static, Printlned>,
+    pub(crate) file_events: futures::stream::LocalBoxStream<'static, FileWritten>,
 }
 
 pub(crate) struct Outputs {
@@ -24,6 +34,8 @@ pub(crate) struct Outputs {
     pub(crate) expression_commands: futures::stream::LocalBoxStream<'static, EvaluateExpression>,
     pub(crate) done: futures::future::LocalBoxFuture<'static, anyhow::Result<()>>,
     pub(crate) eprintln_strings: futures::stream::LocalBoxStream<'static, String>,
+    pub(crate) println_strings: futures::stream::LocalBoxStream<'static, String>,
+    pub(crate) file_commands: futures::stream::LocalBoxStream<'static, FileCommand>,
 }
 
 #[derive(Debug)]
@@ -32,46 +44,71 @@ enum OutputEvent {
     ReplCommand(ReplCommand),
     ExpressionCommand(EvaluateExpression),
     Eprintln(String),
+    Println(String),
+    FileCommand(FileCommand),
 }
 
 #[derive(Debug)]
 enum InputEvent {
-    Example(Example),
+    Discovered(Vec<ExampleId>),
+    Example(Box<Example>),
     ReplEvent(ReplEvent),
     ExpressionEvent(ExpressionEvent),
     Eprintlned,
+    Printlned,
+    FileWritten(FileWritten),
 }
 
 pub(crate) fn app(inputs: Inputs) -> Outputs {
     let Inputs {
+        report_config,
+        eval_mode,
+        documents,
         examples,
         repl_events,
         expression_events,
         eprintln_events,
+        println_events,
+        file_events,
     } = inputs;
 
-    let examples = futures::stream::iter(examples).map(InputEvent::Example);
+    let discovered = examples.iter().flat_map(Example::ids).cloned().collect();
+    let examples =
+        futures::stream::once(futures::future::ready(InputEvent::Discovered(discovered))).chain(
+            futures::stream::iter(examples)
+                .map(Box::new)
+                .map(InputEvent::Example),
+        );
 
     let repl_events = repl_events.map(InputEvent::ReplEvent);
     let expression_events = expression_events.map(InputEvent::ExpressionEvent);
     let eprintln_events = eprintln_events.map(|_| InputEvent::Eprintlned);
+    let println_events = println_events.map(|_| InputEvent::Printlned);
+    let file_events = file_events.map(InputEvent::FileWritten);
 
     let input_events = futures::stream::select_all([
         examples.boxed_local(),
         repl_events.boxed_local(),
         expression_events.boxed_local(),
         eprintln_events.boxed_local(),
+        println_events.boxed_local(),
+        file_events.boxed_local(),
     ]);
 
     let output_events = input_events
-        .scan(State::default(), |state, event| {
-            let output = state.event(event);
-
-            futures::future::ready(Some(output))
-        })
+        .scan(
+            State::new(report_config, eval_mode, documents),
+            |state, event| {
+                let output = state.event(event);
+
+                futures::future::ready(Some(output))
+            },
+        )
         .flat_map(futures::stream::iter);
 
     let (eprintln_sender, eprintln_strings) = futures::channel::mpsc::unbounded::<String>();
+    let (println_sender, println_strings) = futures::channel::mpsc::unbounded::<String>();
+    let (file_commands_sender, file_commands) = futures::channel::mpsc::unbounded::<FileCommand>();
     let (repl_commands_sender, repl_commands) = futures::channel::mpsc::unbounded::<ReplCommand>();
     let (expression_commands_sender, expression_commands) =
         futures::channel::mpsc::unbounded::<EvaluateExpression>();
@@ -106,10 +143,26 @@ pub(crate) fn app(inputs: Inputs) -> Outputs {
             }
             .boxed_local()
         }
+        OutputEvent::Println(string) => {
+            let mut sender = println_sender.clone();
+            async move {
+                sender.send(string).await.unwrap();
+            }
+            .boxed_local()
+        }
+        OutputEvent::FileCommand(file_command) => {
+            let mut sender = file_commands_sender.clone();
+            async move {
+                sender.send(file_command).await.unwrap();
+            }
+            .boxed_local()
+        }
     });
 
     Outputs {
         eprintln_strings: eprintln_strings.boxed_local(),
+        println_strings: println_strings.boxed_local(),
+        file_commands: file_commands.boxed_local(),
         repl_commands: repl_commands.boxed_local(),
         expression_commands: expression_commands.boxed_local(),
         done: done
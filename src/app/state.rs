@@ -1,41 +1,95 @@
 pub(crate) mod expression_state;
 pub(crate) mod repl_state;
 
-use anyhow::bail;
+use itertools::Itertools;
 
 use crate::{
     example_id::ExampleId,
-    examples::Example,
-    expression::driver::{EvaluateExpression, ExpressionEvent},
+    examples::{Document, Example},
+    expression::{
+        driver::{EvaluateExpression, ExpressionEvent, ExpressionOutput},
+        ExpectedOutput,
+    },
+    file_driver::{FileCommand, FileWritten},
+    nix_command::{self, EvalMode},
+    normalization::Normalization,
     repl::driver::{ReplCommand, ReplEvent, ReplQuery},
+    report::{
+        self, ExampleResult, ExampleStatus, Failure, FailureKind, Format, Mismatch, ReportConfig,
+        ReportEvent, ReportTarget, RunSummary, Verbosity,
+    },
 };
 
 use self::{
-    expression_state::ExpressionExampleState,
+    expression_state::{ExpressionExampleState, ExpressionStage},
     repl_state::{ReplExampleState, ReplSessionExpecting, ReplSessionLive, ReplSessionState},
 };
 
 use super::{InputEvent, OutputEvent};
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub(super) struct State {
+    report_config: ReportConfig,
+    /// that of Nix, so that failures can be classified as impure
+    eval_mode: Option<EvalMode>,
+    documents: Vec<Document>,
     examples: ExamplesState,
+    total: usize,
+    undispatched: usize,
+    started_at: std::time::Instant,
+    example_started_at: std::collections::BTreeMap<ExampleId, std::time::Instant>,
+    summary: RunSummary,
+    results: std::collections::BTreeMap<ExampleId, ExampleResult>,
+    raw_output: std::collections::BTreeMap<ExampleId, String>,
+    progress_drawn: bool,
+    finished: bool,
     pending_eprintlns: usize,
+    pending_printlns: usize,
+    pending_file_writes: usize,
     error: Option<anyhow::Error>,
 }
 
 impl State {
+    pub(super) fn new(
+        report_config: ReportConfig,
+        eval_mode: Option<EvalMode>,
+        documents: Vec<Document>,
+    ) -> Self {
+        Self {
+            report_config,
+            eval_mode,
+            documents,
+            examples: Default::default(),
+            total: 0,
+            undispatched: 0,
+            started_at: std::time::Instant::now(),
+            example_started_at: Default::default(),
+            summary: Default::default(),
+            results: Default::default(),
+            raw_output: Default::default(),
+            progress_drawn: false,
+            finished: false,
+            pending_eprintlns: 0,
+            pending_printlns: 0,
+            pending_file_writes: 0,
+            error: None,
+        }
+    }
+
     pub(super) fn event(&mut self, event: InputEvent) -> Vec<OutputEvent> {
         let output = match event {
-            InputEvent::Example(example) => self.example(example),
+            InputEvent::Discovered(ids) => self.discovered(ids),
+            InputEvent::Example(example) => self.example(*example),
             InputEvent::ReplEvent(repl_event) => self.repl_event(repl_event),
             InputEvent::ExpressionEvent(expression_event) => {
                 self.expression_event(expression_event)
             }
             InputEvent::Eprintlned => self.eprintlned(),
+            InputEvent::Printlned => self.printlned(),
+            InputEvent::FileWritten(file_written) => self.file_written(file_written),
         };
 
-        let output = match output {
+        let mut output = match output {
             Ok(output) => output,
             Err(error) => {
                 self.error = Some(error);
@@ -43,36 +97,86 @@ impl State {
             }
         };
 
-        if let (Some(error), 0) = (&self.error, self.pending_eprintlns) {
+        if let (Some(error), 0) = (&self.error, self.pending_outputs()) {
             return vec![OutputEvent::Done(Err(anyhow::anyhow!("{error}")))];
         }
 
-        if self.examples.is_empty() && self.pending_eprintlns == 0 {
-            return vec![OutputEvent::Done(Ok(()))];
+        if !self.finished && self.undispatched == 0 && self.examples.is_empty() {
+            self.finished = true;
+            output.extend(self.run_finished());
+        }
+
+        if self.finished && self.pending_outputs() == 0 {
+            return vec![OutputEvent::Done(self.result())];
         }
 
         output
     }
 
+    fn discovered(&mut self, ids: Vec<ExampleId>) -> anyhow::Result<Vec<OutputEvent>> {
+        self.total = ids.len();
+        self.undispatched = ids.len();
+        let mut output = self.report(ReportEvent::RunStarted);
+        output.extend(
+            ids.into_iter()
+                .flat_map(|id| self.report(ReportEvent::ExampleDiscovered(id))),
+        );
+        Ok(output)
+    }
+
     pub(super) fn example(&mut self, example: Example) -> anyhow::Result<Vec<OutputEvent>> {
+        self.undispatched -= example.ids().len();
+
         let (id, example_state, event) = match example {
             Example::Repl(example) => {
                 let example_id = example.id.clone();
-                let example_state = ExampleState::Repl(ReplExampleState::new(example));
-                let event = OutputEvent::ReplCommand(ReplCommand::Spawn(example_id.clone()));
+                let event = OutputEvent::ReplCommand(ReplCommand::Spawn(
+                    example_id.clone(),
+                    example.working_dir.clone(),
+                    example.nix_args.clone(),
+                    example.timeout,
+                ));
+                let example_state = ExampleState::Repl(Box::new(ReplExampleState::new(example)));
                 (example_id, example_state, event)
             }
             Example::Expression(example) => {
                 let example_id = example.id.clone();
-                let example_state = ExampleState::Expression(ExpressionExampleState::Pending);
+                let example_state = ExampleState::Expression(ExpressionExampleState::new(
+                    example.evaluation.expected_json.clone(),
+                    example.evaluation.expected_text.clone(),
+                    example.evaluation.expected_outputs.clone(),
+                    example.evaluation.timeout,
+                    example.evaluation.normalization.clone(),
+                ));
                 let event = OutputEvent::ExpressionCommand(EvaluateExpression(example));
                 (example_id, example_state, event)
             }
+            Example::Skipped(id) => {
+                self.summary.skipped += 1;
+                self.record(&id, ExampleStatus::Skipped, Default::default());
+                return Ok(self.report(ReportEvent::ExampleSkipped(id)));
+            }
+            Example::Source(example) => {
+                let mut output = self.start(example.id.clone());
+                output.extend(match example.diagnostics {
+                    None => self.pass(&example.id),
+                    Some(diagnostics) => self.fail(&example.id, diagnostics),
+                });
+                return Ok(output);
+            }
         };
 
         self.examples.insert(id.clone(), example_state)?;
 
-        Ok(vec![event])
+        let mut output = vec![event];
+        output.extend(self.start(id));
+        Ok(output)
+    }
+
+    fn start(&mut self, id: ExampleId) -> Vec<OutputEvent> {
+        self.example_started_at
+            .insert(id.clone(), std::time::Instant::now());
+        self.report(ReportEvent::ExampleStarted(id))
     }
 
     pub(super) fn repl_event(&mut self, repl_event: ReplEvent) -> anyhow::Result<Vec<OutputEvent>> {
@@ -81,15 +185,16 @@ impl State {
             ReplEvent::Query(id, query, result) => self.repl_event_query(id, query, result),
             ReplEvent::Kill(id) => self.repl_event_kill(id),
             ReplEvent::Read(id, result) => self.repl_event_read(id, result),
+            ReplEvent::TimedOut(id) => self.repl_event_timed_out(id),
             ReplEvent::Error(error) => Err(error.into()),
         }
     }
 
     fn repl_event_spawn(
         &mut self,
-        spawn: Result<ExampleId, std::io::Error>,
+        spawn: Result<(ExampleId, String), std::io::Error>,
     ) -> anyhow::Result<Vec<OutputEvent>> {
-        let id = spawn?;
+        let (id, command_line) = spawn?;
 
         let session = self.examples.get_mut_repl(&id)?;
 
@@ -97,20 +202,26 @@ impl State {
             return Err(anyhow::anyhow!("spawned session {session:?} already live"));
         }
 
-        let session_live = ReplSessionLive::new(session.example.entries.clone());
+        let session_live = ReplSessionLive::new(&session.example);
         session.state = ReplSessionState::Live(session_live);
-        Ok(vec![])
+        Ok(self.report(ReportEvent::NixSpawned(id, command_line)))
     }
 
     fn repl_event_query(
-        &self,
-        _id: ExampleId,
-        _query: ReplQuery,
+        &mut self,
+        id: ExampleId,
+        query: ReplQuery,
         result: anyhow::Result<()>,
     ) -> anyhow::Result<Vec<OutputEvent>> {
         result?;
-        // TODO possibly store this fact
-        Ok(vec![])
+        let block = self
+            .examples
+            .get_mut_repl(&id)?
+            .state
+            .live_mut()?
+            .block
+            .clone();
+        Ok(self.report(ReportEvent::ReplQuerySent(block, query)))
     }
 
     fn repl_event_kill(
@@ -122,10 +233,28 @@ impl State {
         Ok(Vec::new())
     }
 
+    fn repl_event_timed_out(&mut self, id: ExampleId) -> anyhow::Result<Vec<OutputEvent>> {
+        let session = self.examples.get_mut_repl(&id)?;
+
+        // a session that is yet to start or already ending is left to its kill
+        let ReplSessionState::Live(_) = session.state else {
+            return Ok(vec![]);
+        };
+
+        let timeout = session.example.timeout.unwrap_or_default();
+        self.repl_fail(
+            &id,
+            "",
+            Failure::new(format!("timed out after {timeout:?}")),
+        )
+    }
+
     fn repl_event_read(&mut self, id: ExampleId, ch: u8) -> anyhow::Result<Vec<OutputEvent>> {
         let ch = ch as char;
-        let session_live = self.examples.get_mut_repl(&id)?;
-        let session_live = session_live.state.live_mut()?;
+        let raw_output = self.raw_output(&id, ch);
+        let session = self.examples.get_mut_repl(&id)?;
+        let normalization = &session.example.normalization;
+        let session_live = session.state.live_mut()?;
 
         let output = match &mut session_live.expecting {
             ReplSessionExpecting::ClearlineBeforeInitialPrompt { cl_progress } => {
@@ -175,24 +304,20 @@ impl State {
                 };
 
                 let result = Self::sanitize(result)?;
-                let result = result.trim_end_matches('\n');
-
-                if result != expected_result.as_str() {
-                    anyhow::bail!(indoc::formatdoc! {"
-                        {id}
-
-                        Actual:
-
-                        ```
-                        {result}
-                        ```
-
-                        Expected:
-
-                        ```
-                        {expected_result}
-                        ```"
-                    })
+                let result = normalization.apply(result.trim_end_matches('\n'));
+
+                if let Some(expected_result) = expected_result
+                    .as_ref()
+                    .map(|expected_result| normalization.apply(expected_result))
+                    .filter(|expected_result| result != *expected_result)
+                {
+                    let failure = Failure::mismatches(vec![Mismatch {
+                        subject: None,
+                        language: "",
+                        actual: result.clone(),
+                        expected: expected_result,
+                    }]);
+                    break 'arm self.repl_fail(&id, &result, failure)?;
                 }
 
                 self.next_query(&id)?
@@ -208,7 +333,23 @@ impl State {
             }
         };
 
-        Ok(output)
+        Ok(raw_output.into_iter().chain(output).collect())
+    }
+
+    fn raw_output(&mut self, id: &ExampleId, ch: char) -> Vec<OutputEvent> {
+        if self.report_config.verbosity < Verbosity::VeryVerbose {
+            return vec![];
+        }
+
+        let line = self.raw_output.entry(id.clone()).or_default();
+        line.push(ch);
+
+        if ch != '\n' {
+            return vec![];
+        }
+
+        let line = std::mem::take(line);
+        self.report(ReportEvent::ChildOutput(id.clone(), line))
     }
 
     fn next_query(&mut self, id: &ExampleId) -> anyhow::Result<Vec<OutputEvent>> {
@@ -218,7 +359,7 @@ impl State {
             anyhow::bail!("expected session {id} to be live");
         };
 
-        let Some(entry) = session_live.next() else {
+        let Some((block, entry)) = session_live.next() else {
             return self.session_end(id);
         };
 
@@ -227,19 +368,308 @@ impl State {
             expected_result: entry.expected_result,
         };
 
-        Ok(vec![OutputEvent::ReplCommand(ReplCommand::Query(
+        let previous_block = std::mem::replace(&mut session_live.block, block.clone());
+
+        let mut output = Vec::new();
+
+        // the blocks of a named session share it
+        if previous_block != block {
+            output.extend(self.pass(&previous_block));
+            output.extend(self.start(block));
+        }
+
+        output.push(OutputEvent::ReplCommand(ReplCommand::Query(
             id.clone(),
-            entry.query.clone(),
-        ))])
+            entry.query,
+        )));
+
+        Ok(output)
     }
 
     fn session_end(&mut self, id: &ExampleId) -> anyhow::Result<Vec<OutputEvent>> {
         let session = self.examples.get_mut_repl(id)?;
+        let block = session.state.live_mut()?.block.clone();
         session.state = ReplSessionState::Killing;
-        Ok(vec![
-            OutputEvent::ReplCommand(ReplCommand::Kill(id.clone())),
-            self.eprintln(Self::fmt_pass(id)),
-        ])
+        let mut output = vec![OutputEvent::ReplCommand(ReplCommand::Kill(id.clone()))];
+        output.extend(self.pass(&block));
+        Ok(output)
+    }
+
+    fn repl_fail(
+        &mut self,
+        id: &ExampleId,
+        nix_output: &str,
+        failure: Failure,
+    ) -> anyhow::Result<Vec<OutputEvent>> {
+        let session = self.examples.get_mut_repl(id)?;
+        let session_live = session.state.live_mut()?;
+        let block = session_live.block.clone();
+        let remaining_blocks = session_live
+            .map(|(block, _entry)| block)
+            .dedup()
+            .filter(|remaining_block| remaining_block != &block)
+            .collect::<Vec<_>>();
+        session.state = ReplSessionState::Killing;
+
+        let mut output = vec![OutputEvent::ReplCommand(ReplCommand::Kill(id.clone()))];
+        output.extend(self.nix_fail(&block, nix_output, failure));
+        remaining_blocks.into_iter().for_each(|remaining_block| {
+            output.extend(self.start(remaining_block.clone()));
+            let diagnostics = format!("not run because {block} of the same session failed");
+            output.extend(self.fail(&remaining_block, diagnostics));
+        });
+        Ok(output)
+    }
+
+    fn pass(&mut self, id: &ExampleId) -> Vec<OutputEvent> {
+        self.summary.passed += 1;
+        let duration = self.example_duration(id);
+        self.record(id, ExampleStatus::Passed, duration);
+        self.report(ReportEvent::ExamplePassed(id.clone(), duration))
+    }
+
+    fn fail(&mut self, id: &ExampleId, diagnostics: String) -> Vec<OutputEvent> {
+        self.fail_with(id, Failure::new(diagnostics))
+    }
+
+    /// Fails as impure if an evaluation mode is active and `nix_output` reveals an impurity.
+    fn nix_fail(&mut self, id: &ExampleId, nix_output: &str, failure: Failure) -> Vec<OutputEvent> {
+        let impurity = self
+            .eval_mode
+            .and_then(|_eval_mode| nix_command::impurity(nix_output));
+
+        let failure = match impurity {
+            Some(explanation) => {
+                let summary = format!("impure example: {explanation}");
+                Failure {
+                    kind: FailureKind::Impure,
+                    diagnostics: format!("{summary}\n\n{}", failure.diagnostics),
+                    summary: Some(summary),
+                    ..failure
+                }
+            }
+            None => failure,
+        };
+
+        self.fail_with(id, failure)
+    }
+
+    fn fail_with(&mut self, id: &ExampleId, failure: Failure) -> Vec<OutputEvent> {
+        self.summary.failed += 1;
+        let duration = self.example_duration(id);
+        let (kind, diagnostics) = (failure.kind, failure.diagnostics.clone());
+        self.record(id, ExampleStatus::Failed(failure), duration);
+        self.report(ReportEvent::ExampleFailed(
+            id.clone(),
+            duration,
+            kind,
+            diagnostics,
+        ))
+    }
+
+    fn record(&mut self, id: &ExampleId, status: ExampleStatus, duration: std::time::Duration) {
+        self.results
+            .insert(id.clone(), ExampleResult { status, duration });
+    }
+
+    fn example_duration(&mut self, id: &ExampleId) -> std::time::Duration {
+        self.example_started_at
+            .remove(id)
+            .map(|started_at| started_at.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn run_finished(&mut self) -> Vec<OutputEvent> {
+        self.summary.duration = self.started_at.elapsed();
+        let mut output = self.report(ReportEvent::RunFinished(self.summary.clone()));
+
+        let file_commands = self
+            .report_config
+            .targets
+            .iter()
+            .map(|target| match target {
+                ReportTarget::Html(path) => {
+                    let html = report::html::render(&self.documents, &self.results, &self.summary);
+                    FileCommand::Write(path.clone(), html)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        output.extend(
+            file_commands
+                .into_iter()
+                .map(|file_command| self.file_command(file_command)),
+        );
+
+        output
+    }
+
+    fn result(&self) -> anyhow::Result<()> {
+        let failures = self
+            .results
+            .iter()
+            .filter_map(|(id, result)| {
+                let diagnostics = result.status.diagnostics()?;
+                Some(format!("{id}\n\n{diagnostics}"))
+            })
+            .collect::<Vec<_>>();
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("{}", failures.join("\n\n")))
+        }
+    }
+
+    fn report(&mut self, event: ReportEvent) -> Vec<OutputEvent> {
+        match self.report_config.format {
+            Format::Human => self.report_human(event),
+            Format::Json => event
+                .to_json()
+                .map(|json| self.println(json.to_string()))
+                .into_iter()
+                .collect(),
+            Format::Github => self.report_github(event),
+        }
+    }
+
+    fn report_human(&mut self, event: ReportEvent) -> Vec<OutputEvent> {
+        let line = self.human_line(&event);
+
+        if !self.report_config.progress {
+            return line.map(|line| self.eprintln(line)).into_iter().collect();
+        }
+
+        let progress_changed = matches!(
+            event,
+            ReportEvent::ExampleStarted(_)
+                | ReportEvent::ExamplePassed(..)
+                | ReportEvent::ExampleFailed(..)
+                | ReportEvent::ExampleSkipped(_)
+        );
+
+        if line.is_none() && !progress_changed {
+            return vec![];
+        }
+
+        let mut text = String::new();
+
+        if self.progress_drawn {
+            text.push_str(CLEAR_PREVIOUS_LINE);
+        }
+
+        if let ReportEvent::RunFinished(_) = event {
+            self.progress_drawn = false;
+            text.push_str(&line.unwrap_or_default());
+        } else {
+            self.progress_drawn = true;
+            if let Some(line) = line {
+                text.push_str(&line);
+                text.push('\n');
+            }
+            text.push_str(&self.fmt_progress());
+        }
+
+        vec![self.eprintln(text)]
+    }
+
+    fn human_line(&self, event: &ReportEvent) -> Option<String> {
+        let ReportConfig {
+            verbosity,
+            progress,
+            stream,
+            ..
+        } = self.report_config;
+
+        match event {
+            ReportEvent::ExamplePassed(id, _duration)
+                if stream && verbosity >= Verbosity::Normal =>
+            {
+                Some(Self::fmt_pass(id))
+            }
+            ReportEvent::NixSpawned(id, command_line) if verbosity >= Verbosity::Verbose => {
+                Some(format!("{id}: {command_line}"))
+            }
+            ReportEvent::ReplQuerySent(id, query) if verbosity >= Verbosity::Verbose => {
+                Some(format!("{id}: nix-repl> {}", query.trim_end()))
+            }
+            ReportEvent::ChildOutput(id, output) if verbosity >= Verbosity::VeryVerbose => {
+                Some(format!("{id}: {output:?}"))
+            }
+            ReportEvent::RunFinished(summary) => {
+                let buffered_passes = self
+                    .results
+                    .iter()
+                    .filter(|_| !stream && verbosity >= Verbosity::Normal)
+                    .filter(|(_id, result)| matches!(result.status, ExampleStatus::Passed))
+                    .map(|(id, _result)| Self::fmt_pass(id));
+
+                let summary =
+                    (verbosity != Verbosity::Normal || progress).then(|| summary.to_human());
+
+                let lines = buffered_passes.chain(summary).collect::<Vec<_>>();
+                (!lines.is_empty()).then(|| lines.join("\n"))
+            }
+            _ => None,
+        }
+    }
+
+    fn fmt_progress(&self) -> String {
+        const SHOWN_RUNNING: usize = 3;
+
+        let total = self.total;
+        let running = &self.example_started_at;
+        let mut running_ids = running.keys().take(SHOWN_RUNNING).join(", ");
+
+        if running.len() > SHOWN_RUNNING {
+            running_ids.push_str(&format!(" and {} more", running.len() - SHOWN_RUNNING));
+        }
+
+        format!("[{}/{total}] running: {running_ids}", self.results.len())
+    }
+
+    fn report_github(&mut self, event: ReportEvent) -> Vec<OutputEvent> {
+        match event {
+            ReportEvent::ExampleFailed(id, duration, kind, diagnostics)
+                if self.report_config.stream =>
+            {
+                let annotation = report::github_error_annotation(&id, kind, &diagnostics);
+                let mut output =
+                    self.report_human(ReportEvent::ExampleFailed(id, duration, kind, diagnostics));
+                output.push(self.println(annotation));
+                output
+            }
+            ReportEvent::RunFinished(summary) => {
+                let annotations = self
+                    .results
+                    .iter()
+                    .filter(|_| !self.report_config.stream)
+                    .filter_map(|(id, result)| {
+                        let ExampleStatus::Failed(failure) = &result.status else {
+                            return None;
+                        };
+                        Some(report::github_error_annotation(
+                            id,
+                            failure.kind,
+                            &failure.diagnostics,
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut output = self.report_human(ReportEvent::RunFinished(summary.clone()));
+                output.extend(
+                    annotations
+                        .into_iter()
+                        .map(|annotation| self.println(annotation)),
+                );
+                if let Some(path) = self.report_config.github_step_summary.clone() {
+                    let markdown = report::github_step_summary(&summary, &self.results);
+                    output.push(self.file_command(FileCommand::Append(path, markdown)));
+                }
+                output
+            }
+            event => self.report_human(event),
+        }
     }
 
     fn fmt_pass(id: &ExampleId) -> String {
@@ -251,6 +681,20 @@ impl State {
         OutputEvent::Eprintln(line)
     }
 
+    fn println(&mut self, line: String) -> OutputEvent {
+        self.pending_printlns += 1;
+        OutputEvent::Println(line)
+    }
+
+    fn file_command(&mut self, file_command: FileCommand) -> OutputEvent {
+        self.pending_file_writes += 1;
+        OutputEvent::FileCommand(file_command)
+    }
+
+    fn pending_outputs(&self) -> usize {
+        self.pending_eprintlns + self.pending_printlns + self.pending_file_writes
+    }
+
     fn sanitize(s: &str) -> anyhow::Result<String> {
         let ansi_stripped = strip_ansi_escapes::strip(s)?;
         let string = String::from_utf8(ansi_stripped)?
@@ -262,18 +706,159 @@ impl State {
 
     pub(crate) fn expression_event_output(
         &mut self,
-        expression_output: std::io::Result<(ExampleId, std::process::Output)>,
+        expression_output: std::io::Result<(ExampleId, ExpressionOutput)>,
     ) -> anyhow::Result<Vec<OutputEvent>> {
-        let (example_id, expression_output) = expression_output?;
+        let (
+            example_id,
+            ExpressionOutput {
+                process: expression_output,
+                built_files,
+            },
+        ) = expression_output?;
+
+        let ExampleState::Expression(example_state) = self.examples.remove(&example_id)? else {
+            anyhow::bail!("expected expression example state");
+        };
+
+        let Some(expression_output) = expression_output else {
+            let timeout = example_state.timeout.unwrap_or_default();
+            return Ok(self.fail(&example_id, format!("timed out after {timeout:?}")));
+        };
+
+        let stdout = String::from_utf8_lossy(&expression_output.stdout);
+        let stderr = String::from_utf8_lossy(&expression_output.stderr);
+
+        let mut output = Vec::new();
+
+        if self.report_config.verbosity >= Verbosity::VeryVerbose {
+            stdout
+                .split_inclusive('\n')
+                .chain(stderr.split_inclusive('\n'))
+                .for_each(|line| {
+                    output.extend(self.report(ReportEvent::ChildOutput(
+                        example_id.clone(),
+                        line.to_owned(),
+                    )));
+                });
+        }
 
         if !expression_output.status.success() {
-            let stderr = String::from_utf8_lossy(&expression_output.stderr);
-            bail!("{example_id}\n{stderr}")
+            let failure = Failure {
+                nix_stderr: Some(stderr.to_string()),
+                ..Failure::new(stderr.to_string())
+            };
+            output.extend(self.nix_fail(&example_id, &stderr, failure));
+            return Ok(output);
         }
 
-        self.examples.remove(&example_id)?;
+        let failure = match (example_state.expected_json, example_state.expected_text) {
+            (Some(expected), _) => Self::json_mismatch(&stdout, &expected),
+            (None, Some(expected)) => {
+                Self::text_mismatch(&stdout, &expected, &example_state.normalization)
+            }
+            (None, None) => Self::built_files_mismatch(
+                &example_state.expected_outputs,
+                built_files,
+                &example_state.normalization,
+            ),
+        };
 
-        Ok(vec![self.eprintln(Self::fmt_pass(&example_id))])
+        match failure {
+            None => output.extend(self.pass(&example_id)),
+            Some(failure) => output.extend(self.fail_with(&example_id, failure)),
+        }
+
+        Ok(output)
+    }
+
+    /// A failure unless the output is JSON structurally equal to `expected`.
+    fn json_mismatch(output: &str, expected: &serde_json::Value) -> Option<Failure> {
+        let actual = match serde_json::from_str::<serde_json::Value>(output) {
+            Ok(actual) if actual == *expected => return None,
+            Ok(actual) => actual,
+            Err(error) => {
+                return Some(Failure::new(format!(
+                    "could not parse result as JSON: {error}\n\n{output}"
+                )))
+            }
+        };
+
+        let pretty = |value| serde_json::to_string_pretty(value).unwrap_or_default();
+
+        Some(Failure::mismatches(vec![Mismatch {
+            subject: None,
+            language: "json",
+            actual: pretty(&actual),
+            expected: pretty(expected),
+        }]))
+    }
+
+    /// A failure unless the output equals `expected`, but for trailing whitespace,
+    /// once both are normalized.
+    fn text_mismatch(
+        output: &str,
+        expected: &str,
+        normalization: &Normalization,
+    ) -> Option<Failure> {
+        let (actual, expected) = (normalization.apply(output), normalization.apply(expected));
+        let (actual, expected) = (actual.trim_end(), expected.trim_end());
+        if actual == expected {
+            return None;
+        }
+
+        Some(Failure::mismatches(vec![Mismatch {
+            subject: None,
+            language: "",
+            actual: actual.to_owned(),
+            expected: expected.to_owned(),
+        }]))
+    }
+
+    /// A failure unless each built file has its expected contents, once both are normalized.
+    fn built_files_mismatch(
+        expected_outputs: &[ExpectedOutput],
+        built_files: Vec<std::io::Result<String>>,
+        normalization: &Normalization,
+    ) -> Option<Failure> {
+        let (mismatches, read_errors): (Vec<_>, Vec<_>) = expected_outputs
+            .iter()
+            .zip(built_files)
+            .filter_map(|(expected_output, built_file)| {
+                let file = match &expected_output.path {
+                    Some(path) => format!("`{path}` of the output"),
+                    None => "the output".to_owned(),
+                };
+                let expected = normalization.apply(&expected_output.contents);
+
+                match built_file.map(|actual| normalization.apply(&actual)) {
+                    Ok(actual) if actual == expected => None,
+                    Ok(actual) => Some(Ok(Mismatch {
+                        subject: Some(file),
+                        language: "",
+                        actual: actual.trim_end().to_owned(),
+                        expected: expected.trim_end().to_owned(),
+                    })),
+                    Err(error) => Some(Err(format!("could not read {file}: {error}"))),
+                }
+            })
+            .partition_result();
+
+        if mismatches.is_empty() && read_errors.is_empty() {
+            return None;
+        }
+
+        let failure = Failure::mismatches(mismatches);
+        let diagnostics = read_errors
+            .iter()
+            .chain(Some(&failure.diagnostics).filter(|diagnostics| !diagnostics.is_empty()))
+            .join("\n\n");
+        let summary = (!read_errors.is_empty()).then(|| read_errors.join("\n"));
+
+        Some(Failure {
+            diagnostics,
+            summary,
+            ..failure
+        })
     }
 
     pub(crate) fn expression_event(
@@ -288,18 +873,32 @@ impl State {
 
     fn expression_event_spawn(
         &mut self,
-        result: Result<ExampleId, std::io::Error>,
+        result: Result<(ExampleId, String), std::io::Error>,
     ) -> anyhow::Result<Vec<OutputEvent>> {
-        let example_id = result?;
+        let (example_id, command_line) = result?;
         let example_state = self.examples.get_mut_expression(&example_id)?;
-        *example_state = ExpressionExampleState::Spawned;
-        Ok(vec![])
+        example_state.stage = ExpressionStage::Spawned;
+        Ok(self.report(ReportEvent::NixSpawned(example_id, command_line)))
     }
 
     pub(crate) fn eprintlned(&mut self) -> Result<Vec<OutputEvent>, anyhow::Error> {
         self.pending_eprintlns -= 1;
         Ok(Vec::new())
     }
+
+    pub(crate) fn printlned(&mut self) -> Result<Vec<OutputEvent>, anyhow::Error> {
+        self.pending_printlns -= 1;
+        Ok(Vec::new())
+    }
+
+    fn file_written(
+        &mut self,
+        FileWritten(result): FileWritten,
+    ) -> anyhow::Result<Vec<OutputEvent>> {
+        self.pending_file_writes -= 1;
+        result?;
+        Ok(Vec::new())
+    }
 }
 
 #[derive(Debug, Default)]
@@ -351,12 +950,15 @@ impl ExamplesState {
 
 #[derive(Debug)]
 pub(crate) enum ExampleState {
-    Repl(ReplExampleState),
+    Repl(Box<ReplExampleState>),
     Expression(ExpressionExampleState),
 }
 
 const CLEAR_LINE: &str = "\r\u{1b}[K";
 
+/// Moves the cursor to the start of the previous line and clears it.
+const CLEAR_PREVIOUS_LINE: &str = "\u{1b}[1A\r\u{1b}[K";
+
 #[derive(Debug, Clone)]
 pub struct ClearLineProgress(std::iter::Peekable<std::iter::Enumerate<std::str::Chars<'static>>>);
 
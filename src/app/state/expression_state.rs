@@ -1,5 +1,36 @@
+use crate::{expression::ExpectedOutput, normalization::Normalization};
+
+#[derive(Debug)]
+pub(crate) struct ExpressionExampleState {
+    pub(crate) expected_json: Option<serde_json::Value>,
+    pub(crate) expected_text: Option<String>,
+    pub(crate) expected_outputs: Vec<ExpectedOutput>,
+    pub(crate) timeout: Option<std::time::Duration>,
+    pub(crate) normalization: Normalization,
+    pub(crate) stage: ExpressionStage,
+}
+
+impl ExpressionExampleState {
+    pub(crate) fn new(
+        expected_json: Option<serde_json::Value>,
+        expected_text: Option<String>,
+        expected_outputs: Vec<ExpectedOutput>,
+        timeout: Option<std::time::Duration>,
+        normalization: Normalization,
+    ) -> Self {
+        Self {
+            expected_json,
+            expected_text,
+            expected_outputs,
+            timeout,
+            normalization,
+            stage: Default::default(),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
-pub(crate) enum ExpressionExampleState {
+pub(crate) enum ExpressionStage {
     #[default]
     Pending,
     Spawned,
@@ -1,6 +1,9 @@
-use crate::repl::{
-    driver::LFLine,
-    example::{ReplEntry, ReplExample, ReplExampleEntries},
+use crate::{
+    example_id::ExampleId,
+    repl::{
+        driver::LFLine,
+        example::{ReplEntry, ReplExample},
+    },
 };
 
 use super::ClearLineProgress;
@@ -40,7 +43,9 @@ impl ReplSessionState {
 
 #[derive(Debug)]
 pub(crate) struct ReplSessionLive {
-    pub(crate) iterator: std::vec::IntoIter<ReplEntry>,
+    pub(crate) iterator: std::vec::IntoIter<(ExampleId, ReplEntry)>,
+    /// the block of the current entry
+    pub(crate) block: ExampleId,
     pub(crate) expecting: ReplSessionExpecting,
 }
 
@@ -51,19 +56,20 @@ pub(crate) enum ReplSessionExpecting {
     },
     ClearLineBeforeResult {
         cl_progress: ClearLineProgress,
-        expected_result: ExpectedResult,
+        expected_result: Option<ExpectedResult>,
     },
     ResultAndClearlineBeforeNextPrompt {
         acc: String,
-        expected_result: ExpectedResult,
+        expected_result: Option<ExpectedResult>,
     },
     UnexpectedLine,
 }
 
 impl ReplSessionLive {
-    pub(crate) fn new(entries: ReplExampleEntries) -> Self {
+    pub(crate) fn new(example: &ReplExample) -> Self {
         Self {
-            iterator: entries.into_iter(),
+            iterator: example.entries().into_iter(),
+            block: example.id.clone(),
             expecting: ReplSessionExpecting::ClearlineBeforeInitialPrompt {
                 cl_progress: ClearLineProgress::new(),
             },
@@ -72,7 +78,7 @@ impl ReplSessionLive {
 }
 
 impl Iterator for ReplSessionLive {
-    type Item = ReplEntry;
+    type Item = (ExampleId, ReplEntry);
 
     fn next(&mut self) -> Option<Self::Item> {
         let entry = self.iterator.next()?;
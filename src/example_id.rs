@@ -10,3 +10,13 @@ impl std::fmt::Debug for ExampleId {
         write!(f, "ExampleId({self})")
     }
 }
+
+impl ExampleId {
+    pub(crate) fn source_path(&self) -> &camino::Utf8Path {
+        &self.source_path
+    }
+
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+}
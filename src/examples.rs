@@ -1,64 +1,541 @@
+mod asciidoc;
+mod attributes;
+mod fixtures;
+pub(crate) mod frontmatter;
+mod hidden;
+mod markdown;
+mod nix_doc_comments;
+mod org;
+mod rst;
+mod source;
+
 use crate::example_id::ExampleId;
-use crate::expression::ExpressionExample;
+use crate::expression::{annotation, Backend, Evaluation, ExpectedOutput, ExpressionExample};
+use crate::nix_command::NixArgs;
+use crate::normalization::Normalization;
 use crate::repl::example::ReplExample;
 use crate::repl::example::NIX_REPL_LANG_TAG;
 use anyhow::Context;
+use attributes::Attributes;
+use frontmatter::Frontmatter;
 use itertools::Itertools;
+pub(crate) use source::SourceExample;
 
 #[derive(Debug, Clone)]
 pub(crate) enum Example {
     Repl(ReplExample),
     Expression(ExpressionExample),
+    /// a `source=REFERENCE` code block, already checked
+    Source(SourceExample),
+    Skipped(ExampleId),
+}
+
+impl Example {
+    /// Those of every code block of the example.
+    pub(crate) fn ids(&self) -> Vec<&ExampleId> {
+        match self {
+            Example::Repl(example) => example.ids().collect(),
+            Example::Expression(example) => vec![&example.id],
+            Example::Source(example) => vec![&example.id],
+            Example::Skipped(id) => vec![id],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    pub(crate) path: camino::Utf8PathBuf,
+    pub(crate) contents: String,
+}
+
+impl Document {
+    pub(crate) fn format(&self) -> SourceFormat {
+        match self.path.extension() {
+            Some("nix") => SourceFormat::Nix,
+            Some("rst") => SourceFormat::Rst,
+            Some("adoc" | "asciidoc") => SourceFormat::AsciiDoc,
+            Some("org") => SourceFormat::Org,
+            _ => SourceFormat::Markdown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SourceFormat {
+    Markdown,
+    /// markdown in RFC 145 doc comments
+    Nix,
+    /// reStructuredText
+    Rst,
+    /// AsciiDoc `[source]` listing blocks
+    AsciiDoc,
+    /// Emacs Org mode `#+begin_src` blocks
+    Org,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExamplesConfig {
+    /// prepended to the assertions of `=>` annotated expression examples, e.g. `with lib;`
+    pub(crate) annotation_context: Option<String>,
+    /// bindings of the `nix prelude` blocks of the document
+    pub(crate) prelude: Option<String>,
+    /// in which Nix runs, instead of the directory of each document
+    pub(crate) base_dir: Option<camino::Utf8PathBuf>,
+    /// rewrite `source=REFERENCE` code blocks that are out of sync instead of failing them
+    pub(crate) update_sources: bool,
+    /// evaluate expression examples deeply, as with the `strict` attribute
+    pub(crate) strict: bool,
+    /// print the results of expression examples as JSON, as with the `json` attribute
+    pub(crate) json: bool,
+    /// of expression examples, unless a `backend=BACKEND` attribute is given
+    pub(crate) backend: Backend,
+    /// after which the Nix process of an example is killed and the example fails,
+    /// unless a `timeout=DURATION` attribute is given
+    pub(crate) timeout: Option<std::time::Duration>,
+    /// of the front matter of the document
+    pub(crate) normalization: Normalization,
+}
+
+/// A code block as found in a source,
+/// with the line numbers of its opening line and of the first line of its contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CodeBlock {
+    pub(crate) line: usize,
+    pub(crate) literal_line: usize,
+    pub(crate) info: String,
+    pub(crate) literal: String,
 }
 
-pub(crate) fn obtain(glob: &str) -> anyhow::Result<Vec<Example>> {
+pub(crate) fn read_documents(glob: &str) -> anyhow::Result<Vec<Document>> {
     glob::glob(glob)?
         .map(|path| {
             let path = camino::Utf8PathBuf::try_from(path?)?;
             let contents = std::fs::read_to_string(path.clone())?;
-            anyhow::Ok((path, contents))
+            anyhow::Ok(Document { path, contents })
         })
-        .collect::<Result<Vec<_>, _>>()?
+        .collect()
+}
+
+pub(crate) fn read_stdin(path: camino::Utf8PathBuf) -> anyhow::Result<Document> {
+    let contents = std::io::read_to_string(std::io::stdin()).context("could not read stdin")?;
+    Ok(Document { path, contents })
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Examples {
+    pub(crate) examples: Vec<Example>,
+    /// of the `file=PATH` code blocks of each document, removed when dropped
+    pub(crate) fixture_dirs: Vec<tempfile::TempDir>,
+}
+
+pub(crate) fn obtain(documents: &[Document], config: &ExamplesConfig) -> anyhow::Result<Examples> {
+    documents
+        .iter()
+        .try_fold(Examples::default(), |mut examples, document| {
+            let (document_examples, fixture_dir) = document_examples(document, config)?;
+            examples.examples.extend(document_examples);
+            examples.fixture_dirs.extend(fixture_dir);
+            Ok(examples)
+        })
+}
+
+fn document_examples(
+    document: &Document,
+    config: &ExamplesConfig,
+) -> anyhow::Result<(Vec<Example>, Option<tempfile::TempDir>)> {
+    let frontmatter = match document.format() {
+        SourceFormat::Markdown => {
+            frontmatter::parse(&document.contents).with_context(|| format!("{}", document.path))?
+        }
+        _ => Frontmatter::default(),
+    };
+
+    let code_blocks = match document.format() {
+        SourceFormat::Markdown => markdown::code_blocks(&document.contents),
+        SourceFormat::Nix => nix_doc_comments::code_blocks(&document.contents),
+        SourceFormat::Rst => rst::code_blocks(&document.contents),
+        SourceFormat::AsciiDoc => asciidoc::code_blocks(&document.contents),
+        SourceFormat::Org => org::code_blocks(&document.contents),
+    };
+
+    let (fixtures, code_blocks): (Vec<_>, Vec<_>) = code_blocks
         .into_iter()
-        .flat_map(|(path, contents)| {
-            let arena = comrak::Arena::new();
-            let ast = comrak::parse_document(&arena, &contents, &comrak::ComrakOptions::default());
-            ast.traverse()
-                .filter_map(move |node_edge| match node_edge {
-                    comrak::arena_tree::NodeEdge::Start(node) => {
-                        let ast = node.data.borrow().clone();
-                        Some((path.clone(), ast))
-                    }
-                    comrak::arena_tree::NodeEdge::End(_) => None,
-                })
-                .collect::<Vec<_>>()
+        .map(|code_block| CodeBlock {
+            info: frontmatter.info(&code_block.info),
+            ..code_block
         })
-        .filter_map(|(path, ast)| {
-            if let comrak::nodes::NodeValue::CodeBlock(code_block) = ast.value {
-                let comrak::nodes::NodeCodeBlock { info, literal, .. } = code_block;
-                let line = ast.sourcepos.start.line;
-                let id = ExampleId::new(path, line);
-                let mut info_words = info.split_ascii_whitespace();
-
-                let maybe_result = match (info_words.next(), info_words.contains(&"skip")) {
-                    (_, true) => None,
-                    (Some(NIX_REPL_LANG_TAG), _) => {
-                        let repl_example =
-                            ReplExample::try_new(id.clone(), literal.clone()).map(Example::Repl);
-                        Some(repl_example)
-                    }
-                    (Some("nix"), _) => {
-                        let expression_example =
-                            ExpressionExample::new(id.clone(), literal.clone());
-                        Some(Ok(Example::Expression(expression_example)))
-                    }
-                    _ => None,
-                };
-
-                maybe_result.map(|result| result.context(format!("{id}")))
-            } else {
-                None
+        .partition(|code_block| fixtures::path(code_block).is_some());
+
+    let (sources, code_blocks): (Vec<_>, Vec<_>) = code_blocks
+        .into_iter()
+        .partition(|code_block| source::reference(code_block).is_some());
+
+    let (skipped_sources, sources): (Vec<_>, Vec<_>) =
+        sources.into_iter().partition(|code_block| {
+            Attributes::parse(&code_block.info).is_ok_and(|attributes| attributes.flag("skip"))
+        });
+
+    let (preludes, code_blocks): (Vec<_>, Vec<_>) =
+        code_blocks.into_iter().partition(|code_block| {
+            Attributes::parse(&code_block.info).is_ok_and(|attributes| {
+                attributes.language() == Some("nix") && attributes.flag("prelude")
+            })
+        });
+
+    let prelude = frontmatter
+        .prelude
+        .iter()
+        .map(|prelude| match prelude.ends_with('\n') {
+            true => prelude.clone(),
+            false => format!("{prelude}\n"),
+        })
+        .chain(
+            preludes
+                .iter()
+                .map(|code_block| hidden::reveal(&code_block.literal)),
+        )
+        .join("");
+
+    let config = ExamplesConfig {
+        annotation_context: frontmatter
+            .annotation_context
+            .clone()
+            .or_else(|| config.annotation_context.clone()),
+        prelude: (!prelude.is_empty()).then_some(prelude),
+        base_dir: config.base_dir.clone(),
+        update_sources: config.update_sources,
+        strict: config.strict,
+        json: config.json,
+        backend: config.backend,
+        timeout: frontmatter
+            .timeout
+            .as_deref()
+            .map(attributes::duration)
+            .transpose()
+            .with_context(|| format!("{}", document.path))?
+            .or(config.timeout),
+        normalization: Normalization::new(
+            frontmatter
+                .normalize
+                .iter()
+                .map(|rule| (rule.pattern.as_str(), rule.replacement.as_str())),
+        )
+        .with_context(|| format!("{}", document.path))?,
+    };
+
+    let document_dir = match document.path.parent() {
+        Some(parent) if !parent.as_str().is_empty() => parent.to_owned(),
+        _ => ".".into(),
+    };
+
+    let dir = config.base_dir.as_ref().unwrap_or(&document_dir);
+
+    let source_examples = source::check(document, dir, &sources, config.update_sources)?;
+
+    let fixture_dir = (!fixtures.is_empty())
+        .then(|| fixtures::materialize(&document.path, dir, &fixtures))
+        .transpose()?;
+
+    let working_dir = match &fixture_dir {
+        Some(fixture_dir) => camino::Utf8PathBuf::try_from(fixture_dir.path().to_owned())?,
+        None => dir.clone(),
+    };
+
+    let examples = with_expectations(code_blocks)
+        .into_iter()
+        .flat_map(|(code_block, expected)| {
+            code_block_examples(&document.path, &working_dir, code_block, expected, &config)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let examples = join_sessions(examples)
+        .into_iter()
+        .chain(source_examples.into_iter().map(Example::Source))
+        .chain(skipped_sources.iter().map(|code_block| {
+            Example::Skipped(ExampleId::new(document.path.clone(), code_block.line))
+        }))
+        .map(|example| match (example, config.prelude.as_deref()) {
+            (Example::Repl(example), Some(prelude)) => {
+                let id = example.id.clone();
+                let example = example.with_prelude(prelude).context(format!("{id}"))?;
+                Ok(Example::Repl(example))
             }
+            (example, _) => Ok(example),
         })
-        .try_collect()
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok((examples, fixture_dir))
+}
+
+/// Each code block with the `expected` and `output` blocks that directly follow it,
+/// if it is a `nix` block.
+fn with_expectations(code_blocks: Vec<CodeBlock>) -> Vec<(CodeBlock, Vec<CodeBlock>)> {
+    code_blocks
+        .into_iter()
+        .fold(Vec::new(), |mut paired, code_block| {
+            let is_expectation = Attributes::parse(&code_block.info)
+                .is_ok_and(|attributes| attributes.flag("expected") || attributes.has("output"));
+
+            match paired.last_mut() {
+                Some((example, expectations))
+                    if is_expectation
+                        && Attributes::parse(&example.info)
+                            .is_ok_and(|attributes| attributes.language() == Some("nix")) =>
+                {
+                    expectations.push(code_block)
+                }
+                _ => paired.push((code_block, vec![])),
+            }
+
+            paired
+        })
+}
+
+/// Joins the repl examples of each named session into the first of them.
+fn join_sessions(examples: Vec<Example>) -> Vec<Example> {
+    examples
+        .into_iter()
+        .fold(Vec::new(), |mut joined, example| {
+            let Example::Repl(repl_example) = example else {
+                joined.push(example);
+                return joined;
+            };
+
+            let session = joined.iter_mut().find_map(|joined| match joined {
+                Example::Repl(joined)
+                    if joined.session.is_some() && joined.session == repl_example.session =>
+                {
+                    Some(joined)
+                }
+                _ => None,
+            });
+
+            match session {
+                Some(session) => session.blocks.extend(repl_example.blocks),
+                None => joined.push(Example::Repl(repl_example)),
+            }
+
+            joined
+        })
+}
+
+/// Markdown with the info strings of `nix` and `nix-repl` code blocks reduced to the language
+/// and without their hidden lines, for rendering.
+pub(crate) fn strip_annotations(markdown: &str) -> String {
+    markdown::strip_annotations(markdown, &[NIX_REPL_LANG_TAG, "nix"])
+}
+
+fn code_block_examples(
+    path: &camino::Utf8Path,
+    working_dir: &camino::Utf8Path,
+    code_block: CodeBlock,
+    expectations: Vec<CodeBlock>,
+    config: &ExamplesConfig,
+) -> Vec<anyhow::Result<Example>> {
+    let CodeBlock {
+        line,
+        literal_line,
+        info,
+        literal,
+    } = code_block;
+
+    let id = ExampleId::new(path.to_owned(), line);
+
+    // the info strings of other languages are none of our business
+    let Some(NIX_REPL_LANG_TAG | "nix") = info.split_ascii_whitespace().next() else {
+        return vec![];
+    };
+
+    let attributes = match Attributes::parse(&info) {
+        Ok(attributes) => attributes,
+        Err(error) => return vec![Err(error.context(format!("{id}")))],
+    };
+
+    let nix_args = match NixArgs::new(
+        attributes.values("nix-option"),
+        attributes.values("include"),
+        attributes.values("arg"),
+        attributes.values("argstr"),
+    ) {
+        Ok(nix_args) => nix_args,
+        Err(error) => return vec![Err(error.context(format!("{id}")))],
+    };
+
+    let evaluation = match evaluation(&attributes, config, expectations) {
+        Ok(evaluation) => evaluation,
+        Err(error) => return vec![Err(error.context(format!("{id}")))],
+    };
+
+    let literal = hidden::reveal(&literal);
+    let prelude = config.prelude.as_deref();
+
+    let results = match (attributes.language(), attributes.flag("skip")) {
+        (_, true) => vec![Ok(Example::Skipped(id.clone()))],
+        (Some(language), _)
+            if (evaluation.expected_json.is_some()
+                || evaluation.expected_text.is_some()
+                || evaluation.build
+                || evaluation.flake.is_some())
+                && (language == NIX_REPL_LANG_TAG || annotation::is_annotated(&literal)) =>
+        {
+            vec![Err(anyhow::anyhow!(
+                "only plain `nix` examples can be built, evaluate a flake \
+                or be followed by an `expected` block"
+            ))]
+        }
+        (Some(NIX_REPL_LANG_TAG), _) => {
+            let session = attributes.value("session").map(str::to_owned);
+            let repl_example = ReplExample::try_new(
+                id.clone(),
+                session,
+                working_dir.to_owned(),
+                nix_args,
+                evaluation.timeout,
+                evaluation.normalization.clone(),
+                literal,
+            )
+            .map(Example::Repl);
+            vec![repl_example]
+        }
+        (Some("nix"), _) if annotation::is_annotated(&literal) => {
+            match annotation::parse(&literal) {
+                Ok(pairs) => pairs
+                    .into_iter()
+                    .map(|pair| {
+                        let id = ExampleId::new(path.to_owned(), literal_line + pair.line_index);
+                        let expression = pair.assertion(config.annotation_context.as_deref());
+                        let expression = with_prelude(prelude, expression);
+                        Ok(Example::Expression(ExpressionExample::new(
+                            id,
+                            expression,
+                            working_dir.to_owned(),
+                            nix_args.clone(),
+                            evaluation.clone(),
+                        )))
+                    })
+                    .collect(),
+                Err(error) => vec![Err(error)],
+            }
+        }
+        (Some("nix"), _) => {
+            // the attribute path of a flake is not an expression to evaluate in the prelude
+            let expression = match evaluation.flake {
+                Some(_) => literal,
+                None => with_prelude(prelude, literal),
+            };
+            let expression_example = ExpressionExample::new(
+                id.clone(),
+                expression,
+                working_dir.to_owned(),
+                nix_args,
+                evaluation,
+            );
+            vec![Ok(Example::Expression(expression_example))]
+        }
+        _ => vec![],
+    };
+
+    results
+        .into_iter()
+        .map(|result| result.context(format!("{id}")))
+        .collect()
+}
+
+/// How the expression examples of a code block are evaluated,
+/// and what is expected of them by the blocks that follow it.
+fn evaluation(
+    attributes: &Attributes,
+    config: &ExamplesConfig,
+    expectations: Vec<CodeBlock>,
+) -> anyhow::Result<Evaluation> {
+    let (expected, expected_outputs): (Vec<_>, Vec<_>) =
+        expectations.into_iter().partition(|expectation| {
+            Attributes::parse(&expectation.info).is_ok_and(|attributes| attributes.flag("expected"))
+        });
+    let expected_outputs = expected_outputs
+        .into_iter()
+        .map(|expected_output| {
+            let attributes = Attributes::parse(&expected_output.info)?;
+            Ok(ExpectedOutput {
+                path: attributes.value("output").map(Into::into),
+                contents: expected_output.literal,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    anyhow::ensure!(expected.len() <= 1, "more than one `expected` block");
+    let (expected_json, expected_text) = match expected.into_iter().next() {
+        None => (None, None),
+        Some(expected) if Attributes::parse(&expected.info)?.language() == Some("json") => {
+            let expected_json = serde_json::from_str(&expected.literal)
+                .with_context(|| format!("invalid expected JSON at line {}", expected.line))?;
+            (Some(expected_json), None)
+        }
+        Some(expected) => (None, Some(expected.literal)),
+    };
+
+    let build = attributes.flag("build");
+    anyhow::ensure!(
+        build || expected_outputs.is_empty(),
+        "only `build` examples can be followed by `output` blocks"
+    );
+    anyhow::ensure!(
+        !build || (expected_json.is_none() && expected_text.is_none()),
+        "`build` examples cannot be followed by an `expected` block"
+    );
+
+    let backend = match attributes.value("backend") {
+        None => config.backend,
+        Some(backend) => <Backend as clap::ValueEnum>::from_str(backend, false)
+            .map_err(|_| anyhow::anyhow!("unknown backend {backend:?}"))?,
+    };
+
+    let raw = attributes.flag("raw");
+    let apply = attributes.value("apply").map(str::to_owned);
+    let flake = attributes.value("flake").map(str::to_owned);
+
+    anyhow::ensure!(
+        !build || (!raw && apply.is_none()),
+        "`raw` and `apply` cannot be combined with `build`"
+    );
+    anyhow::ensure!(
+        backend == Backend::NixEval || (!raw && apply.is_none()),
+        "`raw` and `apply` require `backend=nix-eval`"
+    );
+    anyhow::ensure!(
+        backend == Backend::NixEval || build || flake.is_none(),
+        "`flake` requires `backend=nix-eval` or `build`"
+    );
+    anyhow::ensure!(
+        !build || !attributes.flag("json"),
+        "`build` examples cannot be printed as JSON"
+    );
+
+    // `--json` is for the examples that print a result, which `build` examples do not
+    let json = (config.json && !build) || attributes.flag("json") || expected_json.is_some();
+
+    Ok(Evaluation {
+        backend,
+        strict: config.strict || attributes.flag("strict"),
+        json,
+        raw,
+        apply,
+        flake,
+        expected_json,
+        expected_text,
+        build,
+        expected_outputs,
+        timeout: match attributes.value("timeout") {
+            None => config.timeout,
+            Some(timeout) => Some(attributes::duration(timeout)?),
+        },
+        normalization: config.normalization.clone(),
+    })
+}
+
+fn with_prelude(prelude: Option<&str>, expression: String) -> String {
+    match prelude {
+        Some(prelude) => format!("let\n{prelude}in\n{expression}"),
+        None => expression,
+    }
 }
@@ -0,0 +1,174 @@
+use super::CodeBlock;
+
+const LISTING_DELIMITER: &str = "----";
+
+/// Code blocks of `[source,LANGUAGE,...]` listing blocks delimited by `----`.
+/// Further positional attributes such as `skip` and options such as `%skip` and `opts=skip`
+/// become words of the info string and named attributes such as `name=foo` are kept as they are.
+pub(crate) fn code_blocks(contents: &str) -> Vec<CodeBlock> {
+    let lines = contents.lines().collect::<Vec<_>>();
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let info = source_block_info(line)?;
+
+            // block titles and further attribute lines may follow
+            let delimiter_index = lines[index + 1..]
+                .iter()
+                .position(|line| !is_title(line) && !line.starts_with('['))
+                .map(|position| index + 1 + position)?;
+
+            let delimiter = lines[delimiter_index];
+            if !is_listing_delimiter(delimiter) {
+                return None;
+            }
+
+            let content = lines[delimiter_index + 1..]
+                .iter()
+                .take_while(|line| **line != delimiter)
+                .map(|line| format!("{line}\n"))
+                .collect::<Vec<_>>();
+
+            if delimiter_index + 1 + content.len() == lines.len() {
+                // unterminated
+                return None;
+            }
+
+            Some(CodeBlock {
+                line: index + 1,
+                literal_line: delimiter_index + 2,
+                info,
+                literal: content.concat(),
+            })
+        })
+        .collect()
+}
+
+fn source_block_info(line: &str) -> Option<String> {
+    let attributes = line.trim_end().strip_prefix('[')?.strip_suffix(']')?;
+    let mut attributes = split_attributes(attributes).into_iter();
+
+    let style_and_options = attributes.next()?;
+    let mut style_and_options = style_and_options.split('%');
+    if style_and_options.next()? != "source" {
+        return None;
+    }
+
+    let options = style_and_options.map(str::to_owned).collect::<Vec<_>>();
+    let language = attributes.next()?;
+
+    let words = std::iter::once(language)
+        .chain(options)
+        .chain(attributes.flat_map(|attribute| {
+            match attribute.split_once('=') {
+                Some(("opts" | "options", options)) => options
+                    .trim_matches('"')
+                    .split(',')
+                    .map(|option| option.trim().to_owned())
+                    .collect(),
+                _ => vec![attribute],
+            }
+        }))
+        .collect::<Vec<_>>();
+
+    Some(words.join(" "))
+}
+
+/// Splits on commas that are not within double quotes.
+fn split_attributes(attributes: &str) -> Vec<String> {
+    let mut split = vec![String::new()];
+    let mut quoted = false;
+
+    attributes.chars().for_each(|ch| match ch {
+        ',' if !quoted => split.push(String::new()),
+        '"' => {
+            quoted = !quoted;
+            split.last_mut().unwrap().push(ch);
+        }
+        _ => split.last_mut().unwrap().push(ch),
+    });
+
+    split
+        .into_iter()
+        .map(|attribute| attribute.trim().to_owned())
+        .collect()
+}
+
+fn is_title(line: &str) -> bool {
+    line.starts_with('.') && !line.starts_with("..")
+}
+
+fn is_listing_delimiter(line: &str) -> bool {
+    line.len() >= LISTING_DELIMITER.len() && line.chars().all(|ch| ch == '-')
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{code_blocks, CodeBlock};
+
+    #[test]
+    fn finds_code_blocks() {
+        let contents = indoc! {r#"
+            = Title
+
+            [source,nix]
+            ----
+            let a = 1; in a
+            ----
+
+            [source%skip,nix,opts="foo,bar",name=x]
+            .A title
+            -----
+            assert false; null
+            ----
+            -----
+
+            [source,python]
+            ----
+            print(1)
+            ----
+
+            [source,nix-repl,skip]
+            ----
+            nix-repl> 1 + 1
+            2
+
+            ----
+        "#};
+
+        assert_eq!(
+            code_blocks(contents),
+            vec![
+                CodeBlock {
+                    line: 3,
+                    literal_line: 5,
+                    info: "nix".to_owned(),
+                    literal: "let a = 1; in a\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 8,
+                    literal_line: 11,
+                    info: "nix skip foo bar name=x".to_owned(),
+                    literal: "assert false; null\n----\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 15,
+                    literal_line: 17,
+                    info: "python".to_owned(),
+                    literal: "print(1)\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 20,
+                    literal_line: 22,
+                    info: "nix-repl skip".to_owned(),
+                    literal: "nix-repl> 1 + 1\n2\n\n".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+/// The words of an info string: `language flag key=value key="quoted value"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Attributes(Vec<(String, Option<String>)>);
+
+impl Attributes {
+    pub(crate) fn parse(info: &str) -> anyhow::Result<Self> {
+        let mut attributes = vec![];
+        let mut chars = info.chars().peekable();
+
+        loop {
+            while chars.next_if(char::is_ascii_whitespace).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let key =
+                std::iter::from_fn(|| chars.next_if(|c| !c.is_ascii_whitespace() && *c != '='))
+                    .collect::<String>();
+
+            let value = match chars.next_if_eq(&'=') {
+                None => None,
+                Some(_) if chars.next_if_eq(&'"').is_some() => {
+                    let mut value = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(escaped) => value.push(escaped),
+                                None => anyhow::bail!("unterminated value of {key:?}"),
+                            },
+                            Some(c) => value.push(c),
+                            None => anyhow::bail!("unterminated value of {key:?}"),
+                        }
+                    }
+                    Some(value)
+                }
+                Some(_) => Some(
+                    std::iter::from_fn(|| chars.next_if(|c| !c.is_ascii_whitespace())).collect(),
+                ),
+            };
+
+            anyhow::ensure!(!key.is_empty(), "attribute without a name in {info:?}");
+            attributes.push((key, value));
+        }
+
+        Ok(Self(attributes))
+    }
+
+    /// The first word, unless it has a value.
+    pub(crate) fn language(&self) -> Option<&str> {
+        match self.0.first() {
+            Some((language, None)) => Some(language),
+            _ => None,
+        }
+    }
+
+    /// Whether the last occurrence of `key` is bare or `key=true`, so that `key=false` unsets it.
+    pub(crate) fn flag(&self, key: &str) -> bool {
+        self.0
+            .iter()
+            .skip(1)
+            .rev()
+            .find(|(k, _)| k == key)
+            .is_some_and(|(_, value)| matches!(value.as_deref(), None | Some("true")))
+    }
+
+    /// Whether `key` occurs, with or without a value.
+    pub(crate) fn has(&self, key: &str) -> bool {
+        self.0.iter().skip(1).any(|(k, _)| k == key)
+    }
+
+    /// The value of the last occurrence of `key`.
+    pub(crate) fn value(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .skip(1)
+            .rev()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// The values of every occurrence of `key`.
+    pub(crate) fn values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .skip(1)
+            .filter(move |(k, _)| k == key)
+            .filter_map(|(_, value)| value.as_deref())
+    }
+}
+
+/// A duration of whole milliseconds, seconds, minutes or hours, e.g. `500ms`, `30s`, `2m` or `1h`.
+pub(crate) fn duration(value: &str) -> anyhow::Result<std::time::Duration> {
+    let unit_index = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_index);
+    let invalid = || anyhow::anyhow!("invalid duration {value:?}, expected e.g. `30s`");
+    let amount = amount.parse::<u64>().map_err(|_| invalid())?;
+
+    let seconds = match unit {
+        "ms" => return Ok(std::time::Duration::from_millis(amount)),
+        "s" => amount,
+        "m" => amount.saturating_mul(60),
+        "h" => amount.saturating_mul(60 * 60),
+        _ => return Err(invalid()),
+    };
+
+    Ok(std::time::Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{duration, Attributes};
+
+    #[test]
+    fn parse() {
+        let attributes =
+            Attributes::parse(r#" nix skip reason="needs \"network\"" name=foo name=bar "#)
+                .unwrap();
+
+        assert_eq!(attributes.language(), Some("nix"));
+        assert!(attributes.flag("skip"));
+        assert!(!attributes.flag("nix"));
+        assert_eq!(
+            attributes,
+            Attributes(vec![
+                ("nix".to_owned(), None),
+                ("skip".to_owned(), None),
+                ("reason".to_owned(), Some(r#"needs "network""#.to_owned())),
+                ("name".to_owned(), Some("foo".to_owned())),
+                ("name".to_owned(), Some("bar".to_owned())),
+            ])
+        );
+        assert_eq!(attributes.value("reason"), Some(r#"needs "network""#));
+        assert_eq!(attributes.value("name"), Some("bar"));
+        assert_eq!(
+            attributes.values("name").collect::<Vec<_>>(),
+            ["foo", "bar"]
+        );
+        assert_eq!(attributes.value("skip"), None);
+    }
+
+    #[test]
+    fn flag() {
+        let attributes =
+            Attributes::parse("nix a b=true c=false d=yes e e=false f=false f").unwrap();
+
+        assert!(attributes.flag("a"));
+        assert!(attributes.flag("b"));
+        assert!(!attributes.flag("c"));
+        assert!(!attributes.flag("d"));
+        assert!(!attributes.flag("e"));
+        assert!(attributes.flag("f"));
+        assert!(!attributes.flag("g"));
+        assert!(attributes.has("c"));
+    }
+
+    #[test]
+    fn unterminated() {
+        assert!(Attributes::parse(r#"nix reason="needs network"#).is_err());
+    }
+
+    #[test]
+    fn durations() {
+        use std::time::Duration;
+
+        assert_eq!(duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(duration("1h").unwrap(), Duration::from_secs(3600));
+        assert!(duration("30").is_err());
+        assert!(duration("s").is_err());
+        assert!(duration("1.5s").is_err());
+    }
+}
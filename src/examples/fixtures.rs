@@ -0,0 +1,72 @@
+use anyhow::Context;
+
+use crate::example_id::ExampleId;
+
+use super::{attributes::Attributes, hidden, CodeBlock};
+
+/// The path of a `file=PATH` code block, relative to the fixture directory.
+pub(crate) fn path(code_block: &CodeBlock) -> Option<camino::Utf8PathBuf> {
+    let attributes = Attributes::parse(&code_block.info).ok()?;
+    attributes.value("file").map(camino::Utf8PathBuf::from)
+}
+
+/// Writes the contents of `file=PATH` code blocks to a temporary directory,
+/// which is removed when dropped,
+/// and links the entries of `dir` that they do not shadow into it.
+pub(crate) fn materialize(
+    document_path: &camino::Utf8Path,
+    dir: &camino::Utf8Path,
+    fixtures: &[CodeBlock],
+) -> anyhow::Result<tempfile::TempDir> {
+    let fixture_dir = tempfile::Builder::new()
+        .prefix("eelco-fixtures-")
+        .tempdir()
+        .context("could not create fixture directory")?;
+
+    fixtures.iter().try_for_each(|code_block| {
+        let id = ExampleId::new(document_path.to_owned(), code_block.line);
+        let path = path(code_block).context("expected `file=PATH`")?;
+
+        anyhow::ensure!(
+            path.components()
+                .all(|component| matches!(component, camino::Utf8Component::Normal(_))),
+            "{id}: fixture path {path:?} must be relative and without `..`"
+        );
+
+        let path = fixture_dir.path().join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("{id}"))?;
+        }
+        std::fs::write(&path, hidden::reveal(&code_block.literal)).with_context(|| format!("{id}"))
+    })?;
+
+    let dir = std::fs::canonicalize(dir).with_context(|| format!("could not resolve {dir}"))?;
+    overlay(&dir, fixture_dir.path()).with_context(|| {
+        format!(
+            "could not link {} into the fixture directory",
+            dir.display()
+        )
+    })?;
+
+    Ok(fixture_dir)
+}
+
+/// Links the entries of `dir` into `fixture_dir`, except those that fixtures shadow,
+/// descending into the directories that both have.
+fn overlay(dir: &std::path::Path, fixture_dir: &std::path::Path) -> std::io::Result<()> {
+    std::fs::read_dir(dir)?.try_for_each(|entry| {
+        let entry = entry?;
+        let target = fixture_dir.join(entry.file_name());
+
+        match std::fs::symlink_metadata(&target) {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                std::os::unix::fs::symlink(entry.path(), &target)
+            }
+            Err(error) => Err(error),
+            Ok(metadata) if metadata.is_dir() && entry.path().is_dir() => {
+                overlay(&entry.path(), &target)
+            }
+            Ok(_) => Ok(()),
+        }
+    })
+}
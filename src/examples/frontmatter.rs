@@ -0,0 +1,234 @@
+use anyhow::Context;
+use itertools::Itertools;
+
+const DELIMITERS: [&str; 2] = ["---", "+++"];
+
+/// Per-document defaults, from the `eelco` table of a YAML (`---`) or TOML (`+++`) front matter block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Frontmatter {
+    /// skip every example of the document
+    pub(crate) skip: bool,
+    /// info string words added to every code block, e.g. `skip`
+    pub(crate) attributes: Vec<String>,
+    /// overrides `--annotation-context`
+    pub(crate) annotation_context: Option<String>,
+    /// bindings in scope in every example, before those of `nix prelude` blocks
+    pub(crate) prelude: Option<String>,
+    /// `NAME=VALUE` pairs passed to Nix for every example, as with `nix-option`
+    pub(crate) nix_options: Vec<String>,
+    /// of every example, e.g. `30s`, unless a `timeout=DURATION` attribute is given
+    pub(crate) timeout: Option<String>,
+    /// applied to the actual and expected output of every example before they are compared
+    pub(crate) normalize: Vec<NormalizationRule>,
+}
+
+/// A regular expression and what its matches are replaced with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NormalizationRule {
+    pub(crate) pattern: String,
+    pub(crate) replacement: String,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct Document {
+    eelco: Frontmatter,
+}
+
+impl Frontmatter {
+    /// The info string with the words of the front matter appended.
+    pub(crate) fn info(&self, info: &str) -> String {
+        let nix_options = self.nix_options.iter().map(|nix_option| {
+            let nix_option = nix_option.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("nix-option=\"{nix_option}\"")
+        });
+
+        std::iter::once(info.to_owned())
+            .chain(self.attributes.iter().cloned())
+            .chain(nix_options)
+            .chain(self.skip.then(|| "skip".to_owned()))
+            .join(" ")
+    }
+}
+
+/// The delimiter of the front matter `contents` may start with.
+fn delimiter(contents: &str) -> Option<String> {
+    DELIMITERS
+        .into_iter()
+        .find(|delimiter| contents.starts_with(delimiter))
+        .map(str::to_owned)
+}
+
+/// The front matter block of `contents`, delimiters included.
+/// A leading `---` without a closing one is a thematic break instead.
+fn block(contents: &str) -> Option<String> {
+    let mut options = comrak::ComrakOptions::default();
+    options.extension.front_matter_delimiter = delimiter(contents);
+    let arena = comrak::Arena::new();
+    let ast = comrak::parse_document(&arena, contents, &options);
+    let first = ast.first_child()?;
+    let comrak::nodes::NodeValue::FrontMatter(block) = &first.data.borrow().value else {
+        return None;
+    };
+    Some(block.clone())
+}
+
+/// `contents` with the lines of its front matter blanked,
+/// so that they are not parsed as markdown and the lines that follow keep their numbers.
+pub(crate) fn blank(contents: &str) -> std::borrow::Cow<'_, str> {
+    match block(contents) {
+        Some(block) => {
+            let lines = block.matches('\n').count();
+            format!("{}{}", "\n".repeat(lines), &contents[block.len()..]).into()
+        }
+        None => contents.into(),
+    }
+}
+
+pub(crate) fn parse(contents: &str) -> anyhow::Result<Frontmatter> {
+    let Some(block) = block(contents) else {
+        return Ok(Frontmatter::default());
+    };
+
+    let block = block.trim_end();
+    let Some((delimiter, body)) = DELIMITERS.into_iter().find_map(|delimiter| {
+        let body = block.strip_prefix(delimiter)?.strip_suffix(delimiter)?;
+        Some((delimiter, body))
+    }) else {
+        return Ok(Frontmatter::default());
+    };
+
+    if body.trim().is_empty() {
+        return Ok(Frontmatter::default());
+    }
+
+    let document: Document = match delimiter {
+        "---" => serde_yaml::from_str(body).context("could not parse YAML front matter")?,
+        _ => toml::from_str(body).context("could not parse TOML front matter")?,
+    };
+
+    Ok(document.eelco)
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{parse, Frontmatter, NormalizationRule};
+
+    #[test]
+    fn yaml() {
+        let contents = indoc! {"
+            ---
+            title: Unrelated
+            eelco:
+              skip: true
+              attributes: [a, b=c]
+            ---
+
+            # Title
+        "};
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            Frontmatter {
+                skip: true,
+                attributes: vec!["a".to_owned(), "b=c".to_owned()],
+                ..Frontmatter::default()
+            }
+        );
+    }
+
+    #[test]
+    fn toml() {
+        let contents = indoc! {r#"
+            +++
+            [eelco]
+            annotation-context = "with builtins;"
+            timeout = "30s"
+            +++
+        "#};
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            Frontmatter {
+                annotation_context: Some("with builtins;".to_owned()),
+                timeout: Some("30s".to_owned()),
+                ..Frontmatter::default()
+            }
+        );
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(parse("# Title\n").unwrap(), Frontmatter::default());
+        assert_eq!(parse("---\n---\n").unwrap(), Frontmatter::default());
+        assert_eq!(
+            parse("---\n\n# Title after a thematic break\n").unwrap(),
+            Frontmatter::default()
+        );
+    }
+
+    #[test]
+    fn prelude_and_nix_options() {
+        let contents = indoc! {r#"
+            ---
+            eelco:
+              prelude: |
+                two = 2;
+              nix-options: ["experimental-features=pipe-operators"]
+            ---
+        "#};
+
+        let frontmatter = parse(contents).unwrap();
+        assert_eq!(
+            frontmatter,
+            Frontmatter {
+                prelude: Some("two = 2;\n".to_owned()),
+                nix_options: vec!["experimental-features=pipe-operators".to_owned()],
+                ..Frontmatter::default()
+            }
+        );
+        assert_eq!(
+            frontmatter.info("nix"),
+            r#"nix nix-option="experimental-features=pipe-operators""#
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        let contents = indoc! {r#"
+            ---
+            eelco:
+              normalize:
+                - pattern: "/nix/store/[0-9a-z]{32}-"
+                  replacement: "/nix/store/HASH-"
+            ---
+        "#};
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            Frontmatter {
+                normalize: vec![NormalizationRule {
+                    pattern: "/nix/store/[0-9a-z]{32}-".to_owned(),
+                    replacement: "/nix/store/HASH-".to_owned(),
+                }],
+                ..Frontmatter::default()
+            }
+        );
+    }
+
+    #[test]
+    fn blank() {
+        let contents = "+++\n[eelco]\nskip = true\n+++\n\n# Title\n";
+        assert_eq!(super::blank(contents), "\n\n\n\n\n# Title\n");
+    }
+
+    #[test]
+    fn unknown_field() {
+        assert!(parse("---\neelco:\n  skp: true\n---\n").is_err());
+    }
+}
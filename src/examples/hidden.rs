@@ -0,0 +1,46 @@
+/// Marks a line of a `nix` or `nix-repl` code block that is executed but need not be rendered.
+pub(crate) const MARKER: &str = "#~";
+
+/// Whether the line is hidden.
+pub(crate) fn is_hidden(line: &str) -> bool {
+    line.trim_start().starts_with(MARKER)
+}
+
+/// The contents of a code block with the markers of hidden lines removed.
+pub(crate) fn reveal(literal: &str) -> String {
+    literal
+        .split_inclusive('\n')
+        .map(|line| {
+            let Some(hidden) = line.trim_start().strip_prefix(MARKER) else {
+                return line;
+            };
+            hidden.strip_prefix(' ').unwrap_or(hidden)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::reveal;
+
+    #[test]
+    fn reveals() {
+        let literal = indoc! {"
+            #~ let lib = import ./lib.nix; in
+            #~
+            lib.id 1
+        "};
+
+        assert_eq!(
+            reveal(literal),
+            indoc! {"
+                let lib = import ./lib.nix; in
+
+                lib.id 1
+            "}
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use super::{frontmatter, hidden, CodeBlock};
+
+/// Code blocks, with the attributes of an immediately preceding
+/// `<!-- eelco: ATTRIBUTES -->` directive appended to the info string.
+pub(crate) fn code_blocks(contents: &str) -> Vec<CodeBlock> {
+    let contents = frontmatter::blank(contents);
+    let arena = comrak::Arena::new();
+    let ast = comrak::parse_document(&arena, &contents, &comrak::ComrakOptions::default());
+    ast.traverse()
+        .filter_map(|node_edge| match node_edge {
+            comrak::arena_tree::NodeEdge::Start(node) => Some(node),
+            comrak::arena_tree::NodeEdge::End(_) => None,
+        })
+        .filter_map(|node| {
+            let ast = node.data.borrow().clone();
+            let comrak::nodes::NodeValue::CodeBlock(code_block) = ast.value else {
+                return None;
+            };
+
+            let comrak::nodes::NodeCodeBlock {
+                fenced,
+                info,
+                literal,
+                ..
+            } = code_block;
+
+            let directive = node.previous_sibling().and_then(|sibling| {
+                let comrak::nodes::NodeValue::HtmlBlock(html_block) = &sibling.data.borrow().value
+                else {
+                    return None;
+                };
+                directive(&html_block.literal).map(str::to_owned)
+            });
+
+            let info = match directive {
+                Some(directive) => format!("{info} {directive}"),
+                None => info,
+            };
+
+            let line = ast.sourcepos.start.line;
+
+            Some(CodeBlock {
+                line,
+                // the contents of a fenced code block start on the line following the fence
+                literal_line: if fenced { line + 1 } else { line },
+                info,
+                literal,
+            })
+        })
+        .collect()
+}
+
+/// The attributes of an `<!-- eelco: ATTRIBUTES -->` comment.
+fn directive(html: &str) -> Option<&str> {
+    let attributes = html
+        .trim()
+        .strip_prefix("<!--")?
+        .strip_suffix("-->")?
+        .trim_start()
+        .strip_prefix("eelco:")?;
+    Some(attributes.trim())
+}
+
+/// Reduces the info strings of fenced code blocks in `languages` to the language
+/// and drops their hidden lines.
+pub(crate) fn strip_annotations(contents: &str, languages: &[&str]) -> String {
+    let code_blocks = code_blocks(contents)
+        .into_iter()
+        .filter(|code_block| code_block.literal_line != code_block.line)
+        .filter_map(|code_block| {
+            let language = code_block.info.split_ascii_whitespace().next()?.to_owned();
+            languages
+                .contains(&language.as_str())
+                .then_some((language, code_block))
+        })
+        .collect::<Vec<_>>();
+
+    let fence_lines = code_blocks
+        .iter()
+        .map(|(language, code_block)| (code_block.line, language))
+        .collect::<std::collections::BTreeMap<_, _>>();
+
+    let literal_lines = code_blocks
+        .iter()
+        .flat_map(|(_, code_block)| {
+            code_block.literal_line..code_block.literal_line + code_block.literal.lines().count()
+        })
+        .collect::<std::collections::BTreeSet<_>>();
+
+    contents
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line_number = index + 1;
+
+            if literal_lines.contains(&line_number) && hidden::is_hidden(line) {
+                return None;
+            }
+
+            let Some(language) = fence_lines.get(&line_number) else {
+                return Some(line.to_owned());
+            };
+            let fence_start = line.len() - line.trim_start().len();
+            let fence_end = line[fence_start..]
+                .find(|character| character != '`' && character != '~')
+                .map_or(line.len(), |index| fence_start + index);
+            Some(format!("{}{language}\n", &line[..fence_end]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{code_blocks, strip_annotations, CodeBlock};
+
+    #[test]
+    fn directives() {
+        let contents = indoc! {r#"
+            <!-- eelco: skip reason="needs network" -->
+            ```nix
+            null
+            ```
+
+            <!-- not a directive -->
+
+            ```nix
+            null
+            ```
+        "#};
+
+        assert_eq!(
+            code_blocks(contents),
+            vec![
+                CodeBlock {
+                    line: 2,
+                    literal_line: 3,
+                    info: r#"nix skip reason="needs network""#.to_owned(),
+                    literal: "null\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 8,
+                    literal_line: 9,
+                    info: "nix".to_owned(),
+                    literal: "null\n".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_annotations() {
+        let contents = indoc! {"
+            ```nix skip
+            #~ let a = null; in
+            null
+            ```
+
+            ~~~ nix-repl session=a
+            nix-repl> 1
+            1
+            ~~~
+
+            ```sh skip
+            #~ true
+            ```
+        "};
+
+        assert_eq!(
+            strip_annotations(contents, &["nix", "nix-repl"]),
+            indoc! {"
+                ```nix
+                null
+                ```
+
+                ~~~nix-repl
+                nix-repl> 1
+                1
+                ~~~
+
+                ```sh skip
+                #~ true
+                ```
+            "}
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use super::{markdown, CodeBlock};
+
+const DOC_COMMENT_START: &str = "/**";
+const COMMENT_END: &str = "*/";
+
+/// Code blocks in the markdown of the RFC 145 doc comments (`/** ... */`) of a Nix file.
+pub(crate) fn code_blocks(contents: &str) -> Vec<CodeBlock> {
+    doc_comments(contents)
+        .into_iter()
+        .flat_map(|DocComment { line, markdown }| {
+            markdown::code_blocks(&markdown)
+                .into_iter()
+                .map(move |code_block| CodeBlock {
+                    line: code_block.line + line - 1,
+                    literal_line: code_block.literal_line + line - 1,
+                    ..code_block
+                })
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct DocComment {
+    /// line of the opening `/**`, which is also the first line of the markdown
+    line: usize,
+    markdown: String,
+}
+
+/// Doc comments outside of strings and other comments.
+fn doc_comments(contents: &str) -> Vec<DocComment> {
+    let mut doc_comments = Vec::new();
+    let mut index = 0;
+
+    while index < contents.len() {
+        let rest = &contents[index..];
+
+        let len = if rest.starts_with('#') {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(after_start) = rest
+            .strip_prefix(DOC_COMMENT_START)
+            // `/**/` is an empty regular comment
+            .filter(|after_start| !after_start.starts_with('/'))
+        {
+            let Some(end) = after_start.find(COMMENT_END) else {
+                break;
+            };
+
+            doc_comments.push(DocComment {
+                line: contents[..index].matches('\n').count() + 1,
+                markdown: dedent(&after_start[..end]),
+            });
+
+            DOC_COMMENT_START.len() + end + COMMENT_END.len()
+        } else if let Some(after_start) = rest.strip_prefix("/*") {
+            let Some(end) = after_start.find(COMMENT_END) else {
+                break;
+            };
+            "/*".len() + end + COMMENT_END.len()
+        } else if rest.starts_with('"') {
+            string_len(rest)
+        } else if rest.starts_with("''") && !ends_with_identifier(&contents[..index]) {
+            indented_string_len(rest)
+        } else {
+            rest.chars().next().map_or(1, char::len_utf8)
+        };
+
+        index += len;
+    }
+
+    doc_comments
+}
+
+/// The length of the `"` string that `rest` starts with, or of `rest` if it is unterminated.
+fn string_len(rest: &str) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+
+    while let Some((index, char)) = chars.next() {
+        match char {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return index + 1,
+            _ => {}
+        }
+    }
+
+    rest.len()
+}
+
+/// The length of the `''` string that `rest` starts with, or of `rest` if it is unterminated.
+fn indented_string_len(rest: &str) -> usize {
+    let mut index = "''".len();
+
+    while let Some(end) = rest[index..].find("''") {
+        let after_end = index + end + "''".len();
+
+        // the `'''`, `''$` and `''\` escapes
+        index = match rest[after_end..].chars().next() {
+            Some('\'' | '$') => after_end + 1,
+            Some('\\') => {
+                let escaped = rest[after_end + 1..].chars().next();
+                after_end + 1 + escaped.map_or(0, char::len_utf8)
+            }
+            _ => return after_end,
+        };
+    }
+
+    rest.len()
+}
+
+/// Whether `''` following `code` continues an identifier such as `x''` instead of starting a string.
+fn ends_with_identifier(code: &str) -> bool {
+    code.chars()
+        .next_back()
+        .is_some_and(|char| char.is_alphanumeric() || matches!(char, '_' | '\'' | '-'))
+}
+
+/// Removes the indentation common to the lines following the opening line.
+fn dedent(content: &str) -> String {
+    let mut lines = content.split('\n');
+    let first = lines.next().unwrap_or_default().trim_start();
+    let lines = lines.collect::<Vec<_>>();
+
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+
+    std::iter::once(first)
+        .chain(
+            lines
+                .into_iter()
+                .map(|line| line.get(indentation..).unwrap_or_default()),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{code_blocks, doc_comments, CodeBlock, DocComment};
+
+    #[test]
+    fn finds_doc_comments() {
+        let contents = indoc! {"
+            {
+              /* regular comment */
+              /**/
+              /**
+                Adds one.
+
+                # Example
+
+                ```nix
+                inc 1
+                ```
+              */
+              inc = x: x + 1;
+            }
+        "};
+
+        assert_eq!(
+            doc_comments(contents),
+            vec![DocComment {
+                line: 4,
+                markdown: "\nAdds one.\n\n# Example\n\n```nix\ninc 1\n```\n".to_owned(),
+            }]
+        );
+
+        assert_eq!(
+            code_blocks(contents),
+            vec![CodeBlock {
+                line: 9,
+                literal_line: 10,
+                info: "nix".to_owned(),
+                literal: "inc 1\n".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_strings() {
+        let contents = indoc! {r#"
+            {
+              a = "/** not a doc comment \" */";
+              b = ''
+                /** not a doc comment ''' ''${x} ''\n */
+              '';
+              c' = x'' /** doc comment */;
+            }
+        "#};
+
+        assert_eq!(
+            doc_comments(contents),
+            vec![DocComment {
+                line: 6,
+                markdown: "doc comment ".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_comments() {
+        let contents = indoc! {"
+            {
+              # see /** below
+              /* or /** here */
+              a = 1; /** doc comment */
+            }
+        "};
+
+        assert_eq!(
+            doc_comments(contents),
+            vec![DocComment {
+                line: 4,
+                markdown: "doc comment ".to_owned(),
+            }]
+        );
+    }
+}
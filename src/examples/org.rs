@@ -0,0 +1,249 @@
+use super::CodeBlock;
+
+const BEGIN_SRC: &str = "#+begin_src";
+const END_SRC: &str = "#+end_src";
+const RESULTS: &str = "#+results";
+const BEGIN_EXAMPLE: &str = "#+begin_example";
+const END_EXAMPLE: &str = "#+end_example";
+
+/// Code blocks of `#+begin_src LANGUAGE` blocks, each followed by its `#+RESULTS:`, if any.
+/// The header argument `:eval no` (or `never`) becomes the `skip` word of the info string
+/// and other header arguments such as `:name foo` become `name=foo`.
+pub(crate) fn code_blocks(contents: &str) -> Vec<CodeBlock> {
+    let lines = contents.lines().collect::<Vec<_>>();
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let header = strip_prefix_ignore_case(line.trim_start(), BEGIN_SRC)?;
+            let mut header = header.split_ascii_whitespace();
+            let mut info = vec![header.next()?.to_owned()];
+
+            let header = header.collect::<Vec<_>>();
+            header
+                .split(|word| word.starts_with(':'))
+                .skip(1)
+                .zip(header.iter().filter(|word| word.starts_with(':')))
+                .for_each(|(value, key)| {
+                    let key = key.trim_start_matches(':');
+                    let value = value.join(" ");
+                    match (key, value.as_str()) {
+                        ("eval", "no" | "never") => info.push("skip".to_owned()),
+                        (key, "") => info.push(key.to_owned()),
+                        (key, value) if value.contains(' ') => {
+                            info.push(format!("{key}=\"{value}\""))
+                        }
+                        (key, value) => info.push(format!("{key}={value}")),
+                    }
+                });
+
+            let content = block_content(&lines[index + 1..], END_SRC)?;
+            let code_block = CodeBlock {
+                line: index + 1,
+                literal_line: index + 2,
+                info: info.join(" "),
+                literal: literal(content),
+            };
+
+            let results = results(&lines, index + 1 + content.len() + 1);
+            Some(std::iter::once(code_block).chain(results))
+        })
+        .flatten()
+        .collect()
+}
+
+/// The lines up to the `end` line, unless there is none.
+fn block_content<'a>(lines: &'a [&'a str], end: &str) -> Option<&'a [&'a str]> {
+    lines
+        .iter()
+        .position(|line| strip_prefix_ignore_case(line.trim_start(), end).is_some())
+        .map(|end| &lines[..end])
+}
+
+/// The lines, without their common indentation and escapes.
+fn literal(content: &[&str]) -> String {
+    let indentation = content
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+
+    content
+        .iter()
+        .map(|line| {
+            let line = line.get(indentation..).unwrap_or_default();
+            format!("{}\n", unescape(line))
+        })
+        .collect()
+}
+
+/// A `text expected` block of the `#+RESULTS:` keyword at the line with `index`, blank lines aside,
+/// with the contents of the fixed-width (`: `) lines or the `#+begin_example` block that follow it.
+fn results(lines: &[&str], index: usize) -> Option<CodeBlock> {
+    let index = index
+        + lines
+            .get(index..)?
+            .iter()
+            .take_while(|line| line.trim().is_empty())
+            .count();
+
+    // `#+RESULTS:`, possibly with a hash as in `#+RESULTS[HASH]:` and followed by a name
+    let keyword = strip_prefix_ignore_case(lines.get(index)?.trim_start(), RESULTS)?;
+    if !keyword.starts_with([':', '[']) {
+        return None;
+    }
+
+    let first = lines.get(index + 1)?.trim_start();
+    let (literal_index, literal) = if strip_prefix_ignore_case(first, BEGIN_EXAMPLE).is_some() {
+        let content = block_content(&lines[index + 2..], END_EXAMPLE)?;
+        (index + 2, literal(content))
+    } else {
+        let literal = lines[index + 1..]
+            .iter()
+            .map_while(|line| match line.trim_start() {
+                ":" => Some(""),
+                line => line.strip_prefix(": "),
+            })
+            .map(|line| format!("{line}\n"))
+            .collect::<String>();
+        (index + 1, literal)
+    };
+
+    (!literal.is_empty()).then(|| CodeBlock {
+        line: index + 1,
+        literal_line: literal_index + 1,
+        info: "text expected".to_owned(),
+        literal,
+    })
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+/// Org escapes lines starting with `*` or `#+` within blocks with a comma.
+fn unescape(line: &str) -> &str {
+    match line.strip_prefix(',') {
+        Some(rest) if rest.starts_with('*') || rest.starts_with("#+") || rest.starts_with(",") => {
+            rest
+        }
+        _ => line,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{code_blocks, CodeBlock};
+
+    #[test]
+    fn finds_code_blocks() {
+        let contents = indoc! {"
+            * Heading
+
+            #+begin_src nix
+              let a = 1; in a
+            #+end_src
+
+            #+BEGIN_SRC nix :eval no :session foo
+            ,* not a heading
+            #+END_SRC
+
+            #+begin_src nix-repl :results output silent
+            nix-repl> 1 + 1
+            2
+
+            #+end_src
+        "};
+
+        assert_eq!(
+            code_blocks(contents),
+            vec![
+                CodeBlock {
+                    line: 3,
+                    literal_line: 4,
+                    info: "nix".to_owned(),
+                    literal: "let a = 1; in a\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 7,
+                    literal_line: 8,
+                    info: "nix skip session=foo".to_owned(),
+                    literal: "* not a heading\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 11,
+                    literal_line: 12,
+                    info: "nix-repl results=\"output silent\"".to_owned(),
+                    literal: "nix-repl> 1 + 1\n2\n\n".to_owned(),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn results() {
+        let contents = indoc! {"
+            #+begin_src nix
+            1 + 1
+            #+end_src
+
+            #+RESULTS:
+            : 2
+
+            #+begin_src nix :results verbatim
+            { a = 1; }
+            #+end_src
+            #+results[0a1b]: attrs
+            #+begin_example
+            { a = 1; }
+            #+end_example
+
+            #+begin_src nix
+            null
+            #+end_src
+            : not results
+        "};
+
+        assert_eq!(
+            code_blocks(contents),
+            vec![
+                CodeBlock {
+                    line: 1,
+                    literal_line: 2,
+                    info: "nix".to_owned(),
+                    literal: "1 + 1\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 5,
+                    literal_line: 6,
+                    info: "text expected".to_owned(),
+                    literal: "2\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 8,
+                    literal_line: 9,
+                    info: "nix results=verbatim".to_owned(),
+                    literal: "{ a = 1; }\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 11,
+                    literal_line: 13,
+                    info: "text expected".to_owned(),
+                    literal: "{ a = 1; }\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 16,
+                    literal_line: 17,
+                    info: "nix".to_owned(),
+                    literal: "null\n".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use super::CodeBlock;
+
+const DIRECTIVES: [&str; 3] = ["code-block", "code", "sourcecode"];
+
+/// Code blocks of the `code-block` directive (and its `code` and `sourcecode` aliases).
+/// Flag options such as `:skip:` become words of the info string
+/// and options with a value such as `:name: foo` become `name=foo`.
+pub(crate) fn code_blocks(contents: &str) -> Vec<CodeBlock> {
+    let lines = contents.lines().collect::<Vec<_>>();
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let indentation = indentation(line);
+            let directive = line.trim_start().strip_prefix("..")?.trim_start();
+            let (name, language) = directive.split_once("::")?;
+
+            if !DIRECTIVES.contains(&name.trim()) {
+                return None;
+            }
+
+            let mut info = vec![language.trim().to_owned()];
+
+            let body = lines[index + 1..]
+                .iter()
+                .take_while(|line| line.trim().is_empty() || self::indentation(line) > indentation)
+                .collect::<Vec<_>>();
+
+            let options = body
+                .iter()
+                .take_while(|line| line.trim_start().starts_with(':'))
+                .collect::<Vec<_>>();
+
+            options.iter().for_each(|option| {
+                let option = option.trim().trim_start_matches(':');
+                let Some((name, value)) = option.split_once(':') else {
+                    return;
+                };
+                let value = value.trim();
+                if value.is_empty() {
+                    info.push(name.to_owned());
+                } else if value.contains(char::is_whitespace) {
+                    info.push(format!("{name}=\"{value}\""));
+                } else {
+                    info.push(format!("{name}={value}"));
+                }
+            });
+
+            let content = &body[options.len()..];
+            let leading_blank_lines = content
+                .iter()
+                .take_while(|line| line.trim().is_empty())
+                .count();
+            let content = &content[leading_blank_lines..];
+            let trailing_blank_lines = content
+                .iter()
+                .rev()
+                .take_while(|line| line.trim().is_empty())
+                .count();
+            let content = &content[..content.len() - trailing_blank_lines];
+
+            let content_indentation = content
+                .iter()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| self::indentation(line))
+                .min()
+                .unwrap_or_default();
+
+            let literal = content
+                .iter()
+                .map(|line| format!("{}\n", line.get(content_indentation..).unwrap_or_default()))
+                .collect();
+
+            Some(CodeBlock {
+                line: index + 1,
+                literal_line: index + 2 + options.len() + leading_blank_lines,
+                info: info.join(" "),
+                literal,
+            })
+        })
+        .collect()
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{code_blocks, CodeBlock};
+
+    #[test]
+    fn finds_code_blocks() {
+        let contents = indoc! {"
+            Title
+            =====
+
+            .. code-block:: nix
+               :skip:
+               :caption: a caption
+
+               let
+                 a = 1;
+               in
+               a
+
+            .. code:: nix-repl
+
+                nix-repl> 1 + 1
+                2
+
+            Done.
+
+            .. note:: not code
+        "};
+
+        assert_eq!(
+            code_blocks(contents),
+            vec![
+                CodeBlock {
+                    line: 4,
+                    literal_line: 8,
+                    info: "nix skip caption=\"a caption\"".to_owned(),
+                    literal: "let\n  a = 1;\nin\na\n".to_owned(),
+                },
+                CodeBlock {
+                    line: 13,
+                    literal_line: 15,
+                    info: "nix-repl".to_owned(),
+                    literal: "nix-repl> 1 + 1\n2\n".to_owned(),
+                },
+            ]
+        );
+    }
+}
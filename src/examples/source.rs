@@ -0,0 +1,312 @@
+use anyhow::Context;
+
+use crate::example_id::ExampleId;
+
+use super::{attributes::Attributes, CodeBlock, Document};
+
+/// A code block that quotes a file, or part of one, and is checked to be in sync with it.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceExample {
+    pub(crate) id: ExampleId,
+    /// `None` if in sync
+    pub(crate) diagnostics: Option<String>,
+}
+
+/// `PATH`, `PATH#L10`, `PATH#L10-L25` or `PATH#REGION`,
+/// where a region is delimited by lines such as `# region REGION` and `# endregion`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceReference {
+    path: camino::Utf8PathBuf,
+    selection: Selection,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selection {
+    Whole,
+    /// one-based and inclusive
+    Lines(usize, usize),
+    Region(String),
+}
+
+impl std::str::FromStr for SourceReference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((path, fragment)) = s.split_once('#') else {
+            return Ok(Self {
+                path: s.into(),
+                selection: Selection::Whole,
+            });
+        };
+
+        // `L` and a digit select lines, any other fragment names a region
+        if !fragment
+            .strip_prefix('L')
+            .is_some_and(|lines| lines.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            return Ok(Self {
+                path: path.into(),
+                selection: Selection::Region(fragment.to_owned()),
+            });
+        }
+
+        let line = |line: &str| -> Option<usize> { line.strip_prefix('L')?.parse().ok() };
+
+        let (start, end) = match fragment.split_once('-') {
+            None => (line(fragment), line(fragment)),
+            Some((start, end)) => (line(start), line(end)),
+        };
+        let selection = match (start, end) {
+            (Some(start), Some(end)) if 0 < start && start <= end => Selection::Lines(start, end),
+            _ => anyhow::bail!(
+                "invalid lines `{fragment}`, expected `L<n>` or `L<start>-L<end>` from line 1"
+            ),
+        };
+
+        Ok(Self {
+            path: path.into(),
+            selection,
+        })
+    }
+}
+
+impl std::fmt::Display for SourceReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.selection {
+            Selection::Whole => write!(f, "{}", self.path),
+            Selection::Lines(start, end) if start == end => write!(f, "{}#L{start}", self.path),
+            Selection::Lines(start, end) => write!(f, "{}#L{start}-L{end}", self.path),
+            Selection::Region(region) => write!(f, "{}#{region}", self.path),
+        }
+    }
+}
+
+impl SourceReference {
+    /// The referenced text, with a line feed at the end of every line.
+    fn read(&self, dir: &camino::Utf8Path) -> anyhow::Result<String> {
+        let path = dir.join(&self.path);
+        let contents =
+            std::fs::read_to_string(&path).with_context(|| format!("could not read {path}"))?;
+        let lines = contents.lines().collect::<Vec<_>>();
+
+        let selected = match &self.selection {
+            Selection::Whole => &lines[..],
+            Selection::Lines(start, end) => {
+                anyhow::ensure!(*end <= lines.len(), "{path} has only {} lines", lines.len());
+                &lines[start - 1..*end]
+            }
+            Selection::Region(region) => {
+                let start = lines
+                    .iter()
+                    .position(|line| marker(line) == Some(Marker::Start(region)))
+                    .with_context(|| format!("no region {region:?} in {path}"))?;
+                let length = lines[start + 1..]
+                    .iter()
+                    .position(|line| matches!(marker(line), Some(Marker::End(end)) if end.is_empty() || end == region))
+                    .with_context(|| format!("region {region:?} of {path} does not end"))?;
+                &lines[start + 1..start + 1 + length]
+            }
+        };
+
+        Ok(selected.iter().map(|line| format!("{line}\n")).collect())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Marker<'a> {
+    Start(&'a str),
+    End(&'a str),
+}
+
+/// `# region NAME` or `# endregion`, in the comment syntax of most languages.
+fn marker(line: &str) -> Option<Marker<'_>> {
+    let comment = line
+        .trim()
+        .trim_start_matches(['#', '/', '*', '<', '!', '-', ';'])
+        .trim_end_matches(['/', '*', '-', '>'])
+        .trim();
+
+    if let Some(name) = comment.strip_prefix("endregion") {
+        return Some(Marker::End(name.trim()));
+    }
+
+    let name = comment.strip_prefix("region ")?.trim();
+    Some(Marker::Start(name))
+}
+
+/// The reference of a `source=REFERENCE` code block.
+pub(crate) fn reference(code_block: &CodeBlock) -> Option<String> {
+    let attributes = Attributes::parse(&code_block.info).ok()?;
+    attributes.value("source").map(str::to_owned)
+}
+
+/// Checks that each `source=` code block is in sync with the file it quotes,
+/// relative to `dir`.
+/// If `update`, the blocks that are not are updated in the document instead.
+pub(crate) fn check(
+    document: &Document,
+    dir: &camino::Utf8Path,
+    code_blocks: &[CodeBlock],
+    update: bool,
+) -> anyhow::Result<Vec<SourceExample>> {
+    let mut updates = Vec::new();
+
+    let examples = code_blocks
+        .iter()
+        .map(|code_block| {
+            let id = ExampleId::new(document.path.clone(), code_block.line);
+
+            let source = reference(code_block)
+                .context("expected `source=REFERENCE`")
+                .and_then(|reference| reference.parse::<SourceReference>())
+                .and_then(|reference| Ok((reference.read(dir)?, reference)));
+
+            let diagnostics = match source {
+                Ok((source, _reference)) if source == code_block.literal => None,
+                Ok((source, _reference)) if update => {
+                    updates.push((code_block, source));
+                    None
+                }
+                Ok((source, reference)) => Some(
+                    similar::TextDiff::from_lines(&code_block.literal, &source)
+                        .unified_diff()
+                        .header(&id.to_string(), &reference.to_string())
+                        .to_string(),
+                ),
+                Err(error) => Some(format!("{error:#}")),
+            };
+
+            SourceExample { id, diagnostics }
+        })
+        .collect();
+
+    if !updates.is_empty() {
+        std::fs::write(&document.path, updated(&document.contents, &updates))
+            .with_context(|| format!("could not update {}", document.path))?;
+    }
+
+    Ok(examples)
+}
+
+/// The contents of a document with the contents of code blocks replaced.
+fn updated(contents: &str, updates: &[(&CodeBlock, String)]) -> String {
+    let mut lines = contents
+        .split_inclusive('\n')
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    let mut updates = updates.to_vec();
+    updates.sort_by_key(|(code_block, _)| std::cmp::Reverse(code_block.literal_line));
+
+    updates.into_iter().for_each(|(code_block, source)| {
+        let start = code_block.literal_line - 1;
+        let end = (start + code_block.literal.lines().count()).min(lines.len());
+
+        // the contents of a code block may be indented in the document, e.g. in a list item
+        let indentation = lines[start..end]
+            .iter()
+            .zip(code_block.literal.lines())
+            .find(|(_, literal_line)| !literal_line.trim().is_empty())
+            .map(|(line, literal_line)| {
+                let literal_indentation = literal_line.len() - literal_line.trim_start().len();
+                let line_indentation = line.len() - line.trim_start().len();
+                line[..line_indentation.saturating_sub(literal_indentation)].to_owned()
+            })
+            .unwrap_or_default();
+
+        let replacement = source.split_inclusive('\n').map(|line| {
+            if line.trim().is_empty() {
+                line.to_owned()
+            } else {
+                format!("{indentation}{line}")
+            }
+        });
+
+        lines.splice(start..end, replacement);
+    });
+
+    lines.concat()
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{marker, updated, CodeBlock, Marker, Selection, SourceReference};
+
+    #[test]
+    fn parse() {
+        let cases = [
+            ("lib.nix", Selection::Whole),
+            ("lib.nix#L3", Selection::Lines(3, 3)),
+            ("lib.nix#L10-L25", Selection::Lines(10, 25)),
+            ("lib.nix#strings", Selection::Region("strings".to_owned())),
+            ("lib.nix#Lists", Selection::Region("Lists".to_owned())),
+        ];
+
+        cases.into_iter().for_each(|(s, selection)| {
+            let reference = s.parse::<SourceReference>().unwrap();
+            assert_eq!(
+                reference,
+                SourceReference {
+                    path: "lib.nix".into(),
+                    selection
+                }
+            );
+            assert_eq!(reference.to_string(), s);
+        });
+
+        [
+            "lib.nix#L0",
+            "lib.nix#L0-L2",
+            "lib.nix#L5-L3",
+            "lib.nix#L5-",
+            "lib.nix#L1x",
+        ]
+        .into_iter()
+        .for_each(|s| assert!(s.parse::<SourceReference>().is_err(), "{s}"));
+    }
+
+    #[test]
+    fn markers() {
+        assert_eq!(marker("  # region strings"), Some(Marker::Start("strings")));
+        assert_eq!(marker("# endregion"), Some(Marker::End("")));
+        assert_eq!(
+            marker("/* endregion strings */"),
+            Some(Marker::End("strings"))
+        );
+        assert_eq!(marker("# regional"), None);
+    }
+
+    #[test]
+    fn update() {
+        let contents = indoc! {"
+            1. Item
+
+               ```nix source=a.nix
+               old
+               ```
+        "};
+
+        let code_block = CodeBlock {
+            line: 3,
+            literal_line: 4,
+            info: "nix source=a.nix".to_owned(),
+            literal: "old\n".to_owned(),
+        };
+
+        assert_eq!(
+            updated(contents, &[(&code_block, "{\n  new = 1;\n}\n".to_owned())]),
+            indoc! {"
+                1. Item
+
+                   ```nix source=a.nix
+                   {
+                     new = 1;
+                   }
+                   ```
+            "}
+        );
+    }
+}
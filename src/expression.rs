@@ -1,15 +1,79 @@
+pub(crate) mod annotation;
 pub(crate) mod driver;
 
-use crate::example_id::ExampleId;
+use crate::{example_id::ExampleId, nix_command::NixArgs, normalization::Normalization};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ExpressionExample {
     pub(crate) id: ExampleId,
     pub(crate) expression: String,
+    /// of the `nix-instantiate` process
+    pub(crate) working_dir: camino::Utf8PathBuf,
+    pub(crate) nix_args: NixArgs,
+    pub(crate) evaluation: Evaluation,
+}
+
+/// How the expression is evaluated and its result checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Evaluation {
+    pub(crate) backend: Backend,
+    /// `--strict`, so that the result is evaluated deeply
+    pub(crate) strict: bool,
+    /// `--json`, so that the result is printed as JSON
+    pub(crate) json: bool,
+    /// `--raw`, so that a string result is printed as is, with the `nix-eval` backend
+    pub(crate) raw: bool,
+    /// `--apply EXPR`, with the `nix-eval` backend
+    pub(crate) apply: Option<String>,
+    /// `REF` of the installable `REF#ATTRPATH`, where the expression is the attribute path,
+    /// with the `nix-eval` backend or a build
+    pub(crate) flake: Option<String>,
+    /// that of a `json expected` block, compared structurally with the result
+    pub(crate) expected_json: Option<serde_json::Value>,
+    /// that of any other `expected` block, compared with the printed result
+    pub(crate) expected_text: Option<String>,
+    /// `nix build` in a temporary store, instead of evaluating
+    pub(crate) build: bool,
+    /// those of the `output` blocks of a build
+    pub(crate) expected_outputs: Vec<ExpectedOutput>,
+    /// after which the Nix process is killed and the example fails
+    pub(crate) timeout: Option<std::time::Duration>,
+    /// applied to the printed result and to built files, and to what is expected of them
+    pub(crate) normalization: Normalization,
+}
+
+/// The contents of a file of the built output, from an `output=PATH` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExpectedOutput {
+    /// within the output, which is itself the file if `None`
+    pub(crate) path: Option<camino::Utf8PathBuf>,
+    pub(crate) contents: String,
+}
+
+/// The Nix command that evaluates expression examples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Backend {
+    /// `nix-instantiate --expr --eval`
+    #[default]
+    NixInstantiate,
+    /// `nix eval --expr`, with the `nix-command` and `flakes` experimental features enabled
+    NixEval,
 }
 
 impl ExpressionExample {
-    pub(crate) fn new(id: ExampleId, expression: String) -> Self {
-        Self { id, expression }
+    pub(crate) fn new(
+        id: ExampleId,
+        expression: String,
+        working_dir: camino::Utf8PathBuf,
+        nix_args: NixArgs,
+        evaluation: Evaluation,
+    ) -> Self {
+        Self {
+            id,
+            expression,
+            working_dir,
+            nix_args,
+            evaluation,
+        }
     }
 }
@@ -0,0 +1,222 @@
+use anyhow::bail;
+
+const RESULT_PREFIX: &str = "=>";
+
+/// A nixdoc-style pair of an expression and its `=>` annotated result, as in
+///
+/// ```text
+/// lib.strings.toUpper "a"
+/// => "A"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AnnotatedPair {
+    /// index of the first line of the expression within the code block
+    pub(crate) line_index: usize,
+    pub(crate) expression: String,
+    pub(crate) result: String,
+}
+
+impl AnnotatedPair {
+    /// An expression that evaluates to `null` if the result matches and throws otherwise.
+    pub(crate) fn assertion(&self, context: Option<&str>) -> String {
+        let Self {
+            expression, result, ..
+        } = self;
+
+        let context = context
+            .map(|context| format!("{context}\n"))
+            .unwrap_or_default();
+
+        indoc::formatdoc! {"
+            {context}let
+              expected = ({result});
+              actual = ({expression});
+            in
+            if actual == expected
+            then null
+            else throw \"expected ${{builtins.toJSON expected}} but got ${{builtins.toJSON actual}}\"
+        "}
+    }
+}
+
+pub(crate) fn is_annotated(literal: &str) -> bool {
+    literal
+        .lines()
+        .any(|line| line.trim_start().starts_with(RESULT_PREFIX))
+}
+
+/// Parses expressions that are each followed by a line starting with `=>`.
+/// The result is the remainder of that line
+/// and the lines that follow it while it has unclosed brackets or they are indented deeper,
+/// up to a blank line.
+pub(crate) fn parse(literal: &str) -> anyhow::Result<Vec<AnnotatedPair>> {
+    let mut pairs: Vec<AnnotatedPair> = Vec::new();
+    let mut expression: Option<(usize, Vec<&str>)> = None;
+    // of the `=>` line of the last pair, while its result may continue
+    let mut result_indentation: Option<usize> = None;
+
+    for (index, line) in literal.lines().enumerate() {
+        if line.trim().is_empty() {
+            result_indentation = None;
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+
+        if let (Some(indentation), Some(pair)) = (result_indentation, pairs.last_mut()) {
+            if !trimmed.starts_with(RESULT_PREFIX)
+                && (is_unclosed(&pair.result) || line.len() - trimmed.len() > indentation)
+            {
+                pair.result.push('\n');
+                pair.result.push_str(line);
+                continue;
+            }
+            result_indentation = None;
+        }
+
+        if let Some(result) = trimmed.strip_prefix(RESULT_PREFIX) {
+            let Some((line_index, lines)) = expression.take() else {
+                bail!(
+                    "`{RESULT_PREFIX}` on line {} must follow an expression",
+                    index + 1
+                );
+            };
+
+            pairs.push(AnnotatedPair {
+                line_index,
+                expression: lines.join("\n"),
+                result: result.trim().to_owned(),
+            });
+            result_indentation = Some(line.len() - trimmed.len());
+        } else {
+            expression
+                .get_or_insert_with(|| (index, Vec::new()))
+                .1
+                .push(line);
+        }
+    }
+
+    if let Some((line_index, _lines)) = expression {
+        bail!(
+            "expression on line {} must be followed by a `{RESULT_PREFIX}` result",
+            line_index + 1
+        );
+    }
+
+    Ok(pairs)
+}
+
+/// Whether `code` opens more brackets than it closes, outside of strings.
+fn is_unclosed(code: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut chars = code.chars();
+
+    while let Some(char) = chars.next() {
+        match (in_string, char) {
+            (true, '\\') => {
+                chars.next();
+            }
+            (_, '"') => in_string = !in_string,
+            (false, '{' | '[' | '(') => depth += 1,
+            (false, '}' | ']' | ')') => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{parse, AnnotatedPair};
+
+    #[test]
+    fn parse_pairs() {
+        let literal = indoc! {r#"
+            toUpper "a"
+            => "A"
+            concatStrings [
+              "a"
+              "b"
+            ]
+            => "ab"
+
+            length [ ]
+              => 0
+        "#};
+
+        assert_eq!(
+            parse(literal).unwrap(),
+            vec![
+                AnnotatedPair {
+                    line_index: 0,
+                    expression: r#"toUpper "a""#.to_owned(),
+                    result: r#""A""#.to_owned(),
+                },
+                AnnotatedPair {
+                    line_index: 2,
+                    expression: "concatStrings [\n  \"a\"\n  \"b\"\n]".to_owned(),
+                    result: r#""ab""#.to_owned(),
+                },
+                AnnotatedPair {
+                    line_index: 8,
+                    expression: "length [ ]".to_owned(),
+                    result: "0".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_multiline_results() {
+        let literal = indoc! {r#"
+            mapAttrs (name: value: value + 1) { a = 1; }
+            => {
+              a = 2;
+            }
+            splitString "," "a,b"
+            => [ "a"
+                 "b" ]
+            toUpper "{"
+            => "{"
+            id 1
+            => 1
+        "#};
+
+        assert_eq!(
+            parse(literal).unwrap(),
+            vec![
+                AnnotatedPair {
+                    line_index: 0,
+                    expression: "mapAttrs (name: value: value + 1) { a = 1; }".to_owned(),
+                    result: "{\n  a = 2;\n}".to_owned(),
+                },
+                AnnotatedPair {
+                    line_index: 4,
+                    expression: r#"splitString "," "a,b""#.to_owned(),
+                    result: "[ \"a\"\n     \"b\" ]".to_owned(),
+                },
+                AnnotatedPair {
+                    line_index: 7,
+                    expression: r#"toUpper "{""#.to_owned(),
+                    result: r#""{""#.to_owned(),
+                },
+                AnnotatedPair {
+                    line_index: 9,
+                    expression: "id 1".to_owned(),
+                    result: "1".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_failure() {
+        assert!(parse("=> 1\n").is_err());
+        assert!(parse("1\n=> 1\n2\n").is_err());
+    }
+}
@@ -1,34 +1,50 @@
 use futures::{FutureExt, SinkExt, StreamExt};
 
-use crate::example_id::ExampleId;
+use crate::{
+    example_id::ExampleId,
+    nix_command::{self, NixCommandConfig},
+};
 
-use super::ExpressionExample;
+use super::{Backend, ExpressionExample};
 
 #[derive(Debug)]
 pub(crate) struct EvaluateExpression(pub(crate) ExpressionExample);
 
 pub(crate) struct ExpressionDriver {
     sender: futures::channel::mpsc::UnboundedSender<ExpressionEvent>,
+    nix_command: NixCommandConfig,
     nix_processes: Vec<(
         ExampleId,
-        futures::future::LocalBoxFuture<'static, std::io::Result<std::process::Output>>,
+        futures::future::LocalBoxFuture<'static, std::io::Result<ExpressionOutput>>,
     )>,
 }
 
 #[derive(Debug)]
 pub(crate) enum ExpressionEvent {
-    Spawn(std::io::Result<ExampleId>),
-    Output(std::io::Result<(ExampleId, std::process::Output)>),
+    Spawn(std::io::Result<(ExampleId, String)>),
+    Output(std::io::Result<(ExampleId, ExpressionOutput)>),
+}
+
+#[derive(Debug)]
+pub(crate) struct ExpressionOutput {
+    /// `None` if the process was killed after the timeout of the example
+    pub(crate) process: Option<std::process::Output>,
+    /// the contents of the files of the expected outputs of a build,
+    /// read before the temporary store is removed
+    pub(crate) built_files: Vec<std::io::Result<String>>,
 }
 
 impl ExpressionDriver {
-    pub(crate) fn new() -> (
+    pub(crate) fn new(
+        nix_command: NixCommandConfig,
+    ) -> (
         Self,
         futures::stream::LocalBoxStream<'static, ExpressionEvent>,
     ) {
         let (sender, receiver) = futures::channel::mpsc::unbounded();
         let driver = Self {
             sender,
+            nix_command,
             nix_processes: Vec::new(),
         };
         (driver, receiver.boxed_local())
@@ -71,16 +87,168 @@ impl ExpressionDriver {
     }
 
     async fn spawn_nix(&mut self, example: ExpressionExample) {
-        let task = tokio::process::Command::new(env!("NIX_INSTANTIATE_CMD_PATH"))
-            .args(["--expr", "--eval"])
-            .arg(example.expression)
-            .output();
+        let evaluation = &example.evaluation;
+        let nix_args = self
+            .nix_command
+            .args(&example.working_dir, &example.nix_args);
+
+        let store = match evaluation
+            .build
+            .then(|| tempfile::Builder::new().prefix("eelco-store-").tempdir())
+            .transpose()
+        {
+            Ok(store) => store,
+            Err(error) => {
+                self.sender
+                    .send(ExpressionEvent::Spawn(Err(error)))
+                    .await
+                    .unwrap();
+                return;
+            }
+        };
+
+        let mut command = match (&store, evaluation.backend) {
+            (Some(store), _) => {
+                let mut command = self.nix_command.command(env!("NIX_CMD_PATH"));
+                command
+                    .args([
+                        "build",
+                        "--extra-experimental-features",
+                        "nix-command flakes",
+                    ])
+                    .args(self.nix_command.eval_mode.is_none().then_some("--impure"))
+                    .args(nix_args)
+                    .arg("--store")
+                    .arg(store.path())
+                    .args(["--no-link", "--print-out-paths"]);
+                command
+            }
+            (None, Backend::NixInstantiate) => {
+                let mut command = self.nix_command.command(env!("NIX_INSTANTIATE_CMD_PATH"));
+                command
+                    .args(nix_args)
+                    .arg("--eval")
+                    .args(evaluation.strict.then_some("--strict"));
+                command
+            }
+            (None, Backend::NixEval) => {
+                let mut command = self.nix_command.command(env!("NIX_CMD_PATH"));
+                command
+                    .args([
+                        "eval",
+                        "--extra-experimental-features",
+                        "nix-command flakes",
+                    ])
+                    // like `nix-instantiate`, so that relative paths resolve,
+                    // unless an evaluation mode is chosen
+                    .args(self.nix_command.eval_mode.is_none().then_some("--impure"))
+                    .args(nix_args)
+                    .args(evaluation.raw.then_some("--raw"))
+                    .args(
+                        evaluation
+                            .apply
+                            .iter()
+                            .flat_map(|apply| ["--apply", apply.as_str()]),
+                    );
+                command
+            }
+        };
+
+        command.args(evaluation.json.then_some("--json"));
+
+        match &evaluation.flake {
+            Some(flake) => command.arg(installable(flake, &example.expression)),
+            // `nix eval` and `nix build` take the argument that follows `--expr` as the expression
+            None => command.arg("--expr").arg(match self.nix_command.eval_mode {
+                // which would otherwise evaluate to an empty string
+                Some(_) => nix_command::shadow_get_env(&example.expression),
+                None => example.expression,
+            }),
+        };
+
+        command
+            .current_dir(example.working_dir)
+            // so that the process ends with its task, e.g. after a timeout
+            .kill_on_drop(true);
+        let command_line = format!("{:?}", command.as_std());
+
+        let paths = evaluation
+            .expected_outputs
+            .iter()
+            .map(|expected_output| expected_output.path.clone())
+            .collect::<Vec<_>>();
+
+        let timeout = evaluation.timeout;
+
+        let task = async move {
+            let process = match timeout {
+                None => Some(command.output().await?),
+                Some(timeout) => tokio::time::timeout(timeout, command.output())
+                    .await
+                    .ok()
+                    .transpose()?,
+            };
+
+            let built_files = match (&store, &process) {
+                (Some(store), Some(process)) if process.status.success() => {
+                    let out_path = String::from_utf8_lossy(&process.stdout);
+                    let out_path = store.path().join(
+                        out_path
+                            .lines()
+                            .next()
+                            .unwrap_or_default()
+                            .trim_start_matches('/'),
+                    );
+                    paths
+                        .iter()
+                        .map(|path| match path {
+                            Some(path) => std::fs::read_to_string(out_path.join(path)),
+                            None => std::fs::read_to_string(&out_path),
+                        })
+                        .collect()
+                }
+                _ => vec![],
+            };
+
+            if let Some(store) = &store {
+                make_removable(store.path());
+            }
+
+            Ok(ExpressionOutput {
+                process,
+                built_files,
+            })
+        };
 
         self.nix_processes
             .push((example.id.clone(), task.boxed_local()));
         self.sender
-            .send(ExpressionEvent::Spawn(Ok(example.id)))
+            .send(ExpressionEvent::Spawn(Ok((example.id, command_line))))
             .await
             .unwrap();
     }
 }
+
+/// `FLAKE#ATTRPATH`, or the flake itself if the attribute path is empty.
+fn installable(flake: &str, attribute_path: &str) -> String {
+    match attribute_path.trim() {
+        "" => flake.to_owned(),
+        attribute_path => format!("{flake}#{attribute_path}"),
+    }
+}
+
+/// Store paths are read-only, which would keep the temporary store from being removed.
+fn make_removable(path: &std::path::Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()) {
+        return;
+    }
+
+    _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755));
+    std::fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .for_each(|entry| make_removable(&entry.path()));
+}
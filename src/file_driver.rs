@@ -0,0 +1,60 @@
+use anyhow::Context;
+use futures::{FutureExt, SinkExt, StreamExt};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug)]
+pub(crate) enum FileCommand {
+    Write(camino::Utf8PathBuf, String),
+    Append(camino::Utf8PathBuf, String),
+}
+
+#[derive(Debug)]
+pub(crate) struct FileWritten(pub(crate) anyhow::Result<()>);
+
+pub(crate) struct FileDriver {
+    sender: futures::channel::mpsc::UnboundedSender<FileWritten>,
+}
+
+impl FileDriver {
+    pub(crate) fn new() -> (Self, futures::stream::LocalBoxStream<'static, FileWritten>) {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let driver = Self { sender };
+        (driver, receiver.boxed_local())
+    }
+
+    pub(crate) fn init(
+        mut self,
+        mut commands: futures::stream::LocalBoxStream<'static, FileCommand>,
+    ) -> futures::future::LocalBoxFuture<'static, ()> {
+        async move {
+            loop {
+                let command = futures::poll!(&mut commands.next());
+                if let std::task::Poll::Ready(Some(command)) = command {
+                    let written = Self::command(command).await;
+                    self.sender.send(FileWritten(written)).await.unwrap();
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+        .boxed_local()
+    }
+
+    async fn command(command: FileCommand) -> anyhow::Result<()> {
+        match command {
+            FileCommand::Write(path, contents) => tokio::fs::write(&path, contents)
+                .await
+                .with_context(|| format!("failed to write {path}")),
+            FileCommand::Append(path, contents) => async {
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await?
+                    .write_all(contents.as_bytes())
+                    .await
+            }
+            .await
+            .with_context(|| format!("failed to append to {path}")),
+        }
+    }
+}
@@ -6,7 +6,13 @@ mod eprintln_driver;
 pub(crate) mod example_id;
 mod examples;
 mod expression;
+mod file_driver;
+mod mdbook;
+mod nix_command;
+mod normalization;
+mod println_driver;
 pub(crate) mod repl;
+mod report;
 
 use clap::Parser;
 use futures::{FutureExt, StreamExt};
@@ -15,33 +21,246 @@ use itertools::Itertools;
 use crate::{
     app::{Inputs, Outputs},
     eprintln_driver::EprintlnDriver,
-    expression::driver::ExpressionDriver,
+    examples::{Document, Example, Examples, ExamplesConfig},
+    expression::{driver::ExpressionDriver, Backend},
+    file_driver::FileDriver,
+    nix_command::{EvalMode, Hermetic, NixArgs, NixCommandConfig},
+    println_driver::PrintlnDriver,
     repl::driver::ReplDriver,
+    report::{Format, ReportConfig, ReportTarget, Verbosity},
 };
 
 #[derive(Debug, clap::Parser)]
-#[command(version, about)]
+#[command(version, about, subcommand_negates_reqs = true)]
 struct Cli {
-    /// pattern (`glob` crate) of markdown filespaths
-    sources: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// pattern (`glob` crate) of source filespaths, or `-` to read a document from stdin
+    #[arg(required = true)]
+    sources: Option<String>,
+    /// path of the document read from stdin, by which its examples are identified
+    /// and its format is determined
+    #[arg(long, value_name = "PATH", default_value = "-")]
+    stdin_filename: camino::Utf8PathBuf,
+    /// Nix code in which the expressions of `=>` annotated examples are evaluated, e.g. `with lib;`
+    #[arg(long, value_name = "NIX", global = true)]
+    annotation_context: Option<String>,
+    /// how to report progress and results
+    /// [default: `github` if `GITHUB_ACTIONS` is set, otherwise `human`]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// write a report in addition to the output, e.g. `html=report.html`
+    #[arg(long, value_name = "KIND=PATH", global = true)]
+    report: Vec<ReportTarget>,
+    /// only report failures and a summary
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
+    /// also report Nix command lines and repl queries, twice to also report raw Nix output
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// report results as examples finish rather than in source order at the end
+    #[arg(long, global = true)]
+    stream: bool,
+    /// directory in which Nix runs, against which relative paths in examples resolve
+    /// [default: that of each source file]
+    #[arg(long, value_name = "DIR", global = true)]
+    base_dir: Option<camino::Utf8PathBuf>,
+    /// rewrite code blocks that are out of sync with their `source=REFERENCE` instead of failing,
+    /// in source files other than stdin and mdBook chapters
+    #[arg(long, global = true)]
+    update_sources: bool,
+    /// run Nix with a cleared environment and a temporary `HOME`,
+    /// ignoring user configuration and channels
+    #[arg(long, global = true)]
+    hermetic: bool,
+    /// `NIX_PATH` for Nix [default: inherited, empty if hermetic]
+    #[arg(long, value_name = "NIX_PATH", global = true)]
+    nix_path: Option<String>,
+    /// environment variable to pass through to Nix even if hermetic
+    #[arg(long, value_name = "VAR", requires = "hermetic", global = true)]
+    pass_env: Vec<String>,
+    /// evaluate in pure or restricted mode, failing examples that depend on their environment
+    /// as impure
+    #[arg(long, global = true)]
+    eval_mode: Option<EvalMode>,
+    /// path accessible in restricted mode, besides the directory in which Nix runs
+    #[arg(long, value_name = "PATH", global = true)]
+    allow_path: Vec<camino::Utf8PathBuf>,
+    /// Nix option for every example, as with `--option NAME VALUE`
+    #[arg(long, value_name = "NAME=VALUE", global = true)]
+    nix_option: Vec<String>,
+    /// search path entry for every example, as with `-I PATH`
+    #[arg(short = 'I', long, value_name = "PATH", global = true)]
+    include: Vec<String>,
+    /// argument for every example, as with `--arg NAME EXPR`
+    #[arg(long, value_name = "NAME=EXPR", global = true)]
+    arg: Vec<String>,
+    /// string argument for every example, as with `--argstr NAME VALUE`
+    #[arg(long, value_name = "NAME=VALUE", global = true)]
+    argstr: Vec<String>,
+    /// evaluate expression examples deeply, as with the `strict` attribute
+    #[arg(long, global = true)]
+    strict: bool,
+    /// print the results of expression examples as JSON, as with the `json` attribute
+    #[arg(long, global = true)]
+    json: bool,
+    /// Nix command that evaluates expression examples, unless a `backend=BACKEND` attribute is given
+    #[arg(long, value_enum, default_value_t, global = true)]
+    backend: Backend,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// act as an mdBook preprocessor, testing the examples of every chapter
+    ///
+    /// Results are reported on stderr in the `human` format.
+    Mdbook {
+        #[command(subcommand)]
+        command: Option<MdbookCommand>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum MdbookCommand {
+    /// whether a renderer is supported (all are)
+    Supports { renderer: String },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    let examples = examples::obtain(&cli.sources)?;
-    if examples.is_empty() {
-        anyhow::bail!("could not find any examples");
+    let examples_config = ExamplesConfig {
+        annotation_context: cli.annotation_context.clone(),
+        base_dir: cli.base_dir.clone(),
+        update_sources: cli.update_sources,
+        strict: cli.strict,
+        json: cli.json,
+        backend: cli.backend,
+        ..ExamplesConfig::default()
+    };
+
+    // arguments before a subcommand are allowed, so that global ones can precede it
+    if let (Some(Command::Mdbook { .. }), Some(sources)) = (&cli.command, &cli.sources) {
+        anyhow::bail!("`eelco mdbook` tests the chapters of a book rather than {sources:?}");
     }
-    let (repl_driver, repl_events) = ReplDriver::new();
-    let (expression_driver, expression_events) = ExpressionDriver::new();
+
+    match cli.command {
+        Some(Command::Mdbook {
+            command: Some(MdbookCommand::Supports { .. }),
+        }) => Ok(()),
+        Some(Command::Mdbook { command: None }) => {
+            anyhow::ensure!(
+                !cli.update_sources,
+                "`--update-sources` cannot rewrite the chapters of a book"
+            );
+            let report_config = ReportConfig {
+                format: Format::Human,
+                github_step_summary: None,
+                ..report_config(&cli)
+            };
+            mdbook::preprocess(&examples_config, report_config, nix_command_config(&cli)?).await
+        }
+        None => {
+            let documents = match cli.sources.as_deref().unwrap_or_default() {
+                "-" => {
+                    anyhow::ensure!(
+                        !cli.update_sources,
+                        "`--update-sources` cannot rewrite a document read from stdin"
+                    );
+                    vec![examples::read_stdin(cli.stdin_filename.clone())?]
+                }
+                sources => examples::read_documents(sources)?,
+            };
+            let Examples {
+                examples,
+                fixture_dirs: _fixture_dirs,
+            } = examples::obtain(&documents, &examples_config)?;
+            if examples
+                .iter()
+                .all(|example| matches!(example, Example::Skipped(_)))
+            {
+                anyhow::bail!("could not find any examples");
+            }
+            run(
+                report_config(&cli),
+                nix_command_config(&cli)?,
+                documents,
+                examples,
+            )
+            .await
+        }
+    }
+}
+
+fn report_config(cli: &Cli) -> ReportConfig {
+    let format = cli.format.unwrap_or_else(|| {
+        if std::env::var_os("GITHUB_ACTIONS").is_some() {
+            Format::Github
+        } else {
+            Format::Human
+        }
+    });
+
+    let github_step_summary = std::env::var("GITHUB_STEP_SUMMARY")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(camino::Utf8PathBuf::from);
+
+    ReportConfig {
+        format,
+        verbosity: Verbosity::new(cli.quiet, cli.verbose),
+        progress: !cli.quiet && std::io::IsTerminal::is_terminal(&std::io::stderr()),
+        stream: cli.stream,
+        github_step_summary,
+        targets: cli.report.clone(),
+    }
+}
+
+fn nix_command_config(cli: &Cli) -> anyhow::Result<NixCommandConfig> {
+    let hermetic = cli
+        .hermetic
+        .then(|| Hermetic::new(cli.pass_env.clone()))
+        .transpose()?;
+
+    Ok(NixCommandConfig {
+        nix_path: cli.nix_path.clone(),
+        hermetic,
+        eval_mode: cli.eval_mode,
+        allowed_paths: cli.allow_path.clone(),
+        nix_args: NixArgs::new(
+            cli.nix_option.iter().map(String::as_str),
+            cli.include.iter().map(String::as_str),
+            cli.arg.iter().map(String::as_str),
+            cli.argstr.iter().map(String::as_str),
+        )?,
+    })
+}
+
+/// Tests the examples, reporting as configured.
+/// Fails if any example fails.
+pub(crate) async fn run(
+    report_config: ReportConfig,
+    nix_command_config: NixCommandConfig,
+    documents: Vec<Document>,
+    examples: Vec<Example>,
+) -> anyhow::Result<()> {
+    let eval_mode = nix_command_config.eval_mode;
+    let (repl_driver, repl_events) = ReplDriver::new(nix_command_config.clone());
+    let (expression_driver, expression_events) = ExpressionDriver::new(nix_command_config);
     let (eprintln_driver, eprintln_events) = EprintlnDriver::new();
+    let (println_driver, println_events) = PrintlnDriver::new();
+    let (file_driver, file_events) = FileDriver::new();
 
     let inputs = Inputs {
+        report_config,
+        eval_mode,
+        documents,
         examples,
         repl_events: repl_events.boxed_local(),
         expression_events: expression_events.boxed_local(),
         eprintln_events,
+        println_events,
+        file_events,
     };
 
     let outputs = app::app(inputs);
@@ -52,15 +271,21 @@ async fn main() -> anyhow::Result<()> {
         done,
         execution_handle,
         eprintln_strings,
+        println_strings,
+        file_commands,
     } = outputs;
 
     let eprintln_task = eprintln_driver.init(eprintln_strings);
+    let println_task = println_driver.init(println_strings);
+    let file_task = file_driver.init(file_commands);
     let repl_task = repl_driver.init(repl_commands);
     let expression_task = expression_driver.init(expression_commands);
 
     tokio::select! {
         _ = execution_handle.fuse() => unreachable!(),
         _ = eprintln_task.fuse() => unreachable!(),
+        _ = println_task.fuse() => unreachable!(),
+        _ = file_task.fuse() => unreachable!(),
         _ = repl_task.fuse() => unreachable!(),
         _ = expression_task.fuse() => unreachable!(),
         done = done.fuse() => done,
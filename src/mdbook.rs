@@ -0,0 +1,90 @@
+//! The [mdBook preprocessor](https://rust-lang.github.io/mdBook/for_developers/preprocessors.html)
+//! protocol: `[context, book]` JSON on stdin, the book JSON on stdout.
+
+use anyhow::Context;
+use serde_json::Value;
+
+use crate::{
+    examples::{self, Document, Example, Examples, ExamplesConfig},
+    nix_command::NixCommandConfig,
+    report::ReportConfig,
+};
+
+/// Tests the examples of every chapter and writes the book to stdout,
+/// with eelco-only annotations stripped from code blocks if `strip` is set in
+/// `[preprocessor.eelco]` of `book.toml`.
+pub(crate) async fn preprocess(
+    examples_config: &ExamplesConfig,
+    report_config: ReportConfig,
+    nix_command_config: NixCommandConfig,
+) -> anyhow::Result<()> {
+    let input: Value =
+        serde_json::from_reader(std::io::stdin()).context("could not parse preprocessor input")?;
+    let Value::Array(input) = input else {
+        anyhow::bail!("expected preprocessor input `[context, book]`");
+    };
+    let Ok::<[Value; 2], _>([context, mut book]) = input.try_into() else {
+        anyhow::bail!("expected preprocessor input `[context, book]`");
+    };
+
+    let src = camino::Utf8PathBuf::from(context["root"].as_str().unwrap_or_default())
+        .join(context["config"]["book"]["src"].as_str().unwrap_or("src"));
+    let strip = context["config"]["preprocessor"]["eelco"]["strip"]
+        .as_bool()
+        .unwrap_or_default();
+
+    let mut documents = vec![];
+    for_each_chapter(&mut book, &mut |chapter| {
+        let (Some(source_path), Some(contents)) =
+            (chapter["source_path"].as_str(), chapter["content"].as_str())
+        else {
+            // draft chapter
+            return;
+        };
+        documents.push(Document {
+            path: src.join(source_path),
+            contents: contents.to_owned(),
+        });
+    });
+
+    let Examples {
+        examples,
+        fixture_dirs: _fixture_dirs,
+    } = examples::obtain(&documents, examples_config)?;
+    if !examples
+        .iter()
+        .all(|example| matches!(example, Example::Skipped(_)))
+    {
+        crate::run(report_config, nix_command_config, documents, examples).await?;
+    }
+
+    if strip {
+        for_each_chapter(&mut book, &mut |chapter| {
+            let Some(contents) = chapter["content"].as_str() else {
+                return;
+            };
+            chapter["content"] = examples::strip_annotations(contents).into();
+        });
+    }
+
+    serde_json::to_writer(std::io::stdout(), &book)?;
+    Ok(())
+}
+
+/// Chapters in book order, depth first.
+fn for_each_chapter(book: &mut Value, f: &mut impl FnMut(&mut Value)) {
+    fn sections(items: &mut Value, f: &mut impl FnMut(&mut Value)) {
+        let Some(items) = items.as_array_mut() else {
+            return;
+        };
+        items
+            .iter_mut()
+            .filter_map(|item| item.get_mut("Chapter"))
+            .for_each(|chapter| {
+                f(chapter);
+                sections(&mut chapter["sub_items"], f);
+            });
+    }
+
+    sections(&mut book["sections"], f);
+}
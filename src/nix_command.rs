@@ -0,0 +1,269 @@
+//! How the drivers spawn Nix processes.
+
+use anyhow::Context;
+
+/// Applied to every Nix process.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NixCommandConfig {
+    /// `NIX_PATH` of Nix processes, instead of the inherited one
+    pub(crate) nix_path: Option<String>,
+    /// the environment of Nix processes, instead of the inherited one
+    pub(crate) hermetic: Option<Hermetic>,
+    pub(crate) eval_mode: Option<EvalMode>,
+    /// accessible in restricted evaluation, besides the working directory
+    pub(crate) allowed_paths: Vec<camino::Utf8PathBuf>,
+    /// for every example, before those of the example
+    pub(crate) nix_args: NixArgs,
+}
+
+/// Options, search path entries and arguments passed to Nix,
+/// from the command line or from `nix-option=NAME=VALUE`, `include=PATH`, `arg=NAME=EXPR` and
+/// `argstr=NAME=VALUE` attributes of a code block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct NixArgs {
+    /// `--option NAME VALUE`
+    pub(crate) options: Vec<(String, String)>,
+    /// `-I PATH`, which may be `NAME=PATH`
+    pub(crate) include: Vec<String>,
+    /// `--arg NAME EXPR`
+    pub(crate) args: Vec<(String, String)>,
+    /// `--argstr NAME VALUE`
+    pub(crate) argstrs: Vec<(String, String)>,
+}
+
+impl NixArgs {
+    /// From `NAME=VALUE` options, arguments and string arguments.
+    pub(crate) fn new<'a>(
+        options: impl IntoIterator<Item = &'a str>,
+        include: impl IntoIterator<Item = &'a str>,
+        args: impl IntoIterator<Item = &'a str>,
+        argstrs: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<Self> {
+        fn pairs<'a>(
+            pairs: impl IntoIterator<Item = &'a str>,
+        ) -> anyhow::Result<Vec<(String, String)>> {
+            pairs
+                .into_iter()
+                .map(|pair| {
+                    let (name, value) = pair
+                        .split_once('=')
+                        .with_context(|| format!("expected `NAME=VALUE`, got {pair:?}"))?;
+                    Ok((name.to_owned(), value.to_owned()))
+                })
+                .collect()
+        }
+
+        Ok(Self {
+            options: pairs(options)?,
+            include: include.into_iter().map(str::to_owned).collect(),
+            args: pairs(args)?,
+            argstrs: pairs(argstrs)?,
+        })
+    }
+
+    fn to_args(&self) -> impl Iterator<Item = String> + '_ {
+        let options = self
+            .options
+            .iter()
+            .flat_map(|(name, value)| ["--option".to_owned(), name.clone(), value.clone()]);
+        let include = self
+            .include
+            .iter()
+            .flat_map(|path| ["-I".to_owned(), path.clone()]);
+        let args = self
+            .args
+            .iter()
+            .flat_map(|(name, expr)| ["--arg".to_owned(), name.clone(), expr.clone()]);
+        let argstrs = self
+            .argstrs
+            .iter()
+            .flat_map(|(name, value)| ["--argstr".to_owned(), name.clone(), value.clone()]);
+
+        options.chain(include).chain(args).chain(argstrs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum EvalMode {
+    /// `pure-eval`: no access to paths outside of the store, the search path,
+    /// the environment, the time or the system
+    Pure,
+    /// `restrict-eval`: no access to paths outside of the store, the search path
+    /// and allowed paths
+    Restricted,
+}
+
+impl EvalMode {
+    /// The Nix option that enables it.
+    fn option(self) -> (&'static str, &'static str) {
+        match self {
+            EvalMode::Pure => ("pure-eval", "true"),
+            EvalMode::Restricted => ("restrict-eval", "true"),
+        }
+    }
+}
+
+/// A cleared environment with a temporary `HOME`,
+/// so that user and system configuration, channels and caches are not seen.
+#[derive(Debug, Clone)]
+pub(crate) struct Hermetic {
+    /// removed when the last clone is dropped
+    home: std::rc::Rc<tempfile::TempDir>,
+    /// names of variables passed through anyway
+    pass_env: Vec<String>,
+}
+
+impl Hermetic {
+    pub(crate) fn new(pass_env: Vec<String>) -> anyhow::Result<Self> {
+        let home = tempfile::Builder::new()
+            .prefix("eelco-home-")
+            .tempdir()
+            .context("could not create temporary home")?;
+
+        Ok(Self {
+            home: std::rc::Rc::new(home),
+            pass_env,
+        })
+    }
+}
+
+impl NixCommandConfig {
+    /// A command for `program`, with the environment configured.
+    pub(crate) fn command(&self, program: &str) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(program);
+
+        if let Some(hermetic) = &self.hermetic {
+            let home = hermetic.home.path();
+            command
+                .env_clear()
+                .env("HOME", home)
+                .env("XDG_CONFIG_HOME", home.join(".config"))
+                .env("XDG_CACHE_HOME", home.join(".cache"))
+                .env("XDG_DATA_HOME", home.join(".local/share"))
+                .env("XDG_STATE_HOME", home.join(".local/state"))
+                .env("NIX_PATH", "");
+
+            hermetic
+                .pass_env
+                .iter()
+                .filter_map(|name| Some((name, std::env::var_os(name)?)))
+                .for_each(|(name, value)| {
+                    command.env(name, value);
+                });
+
+            // neither `/etc/nix/nix.conf` nor user configuration exist there,
+            // even if these variables are passed
+            command
+                .env("NIX_CONF_DIR", home.join(".nix-conf-dir"))
+                .env("NIX_USER_CONF_FILES", home.join(".nix-user-conf-file"))
+                .env("NIX_CONFIG", self.nix_config());
+        }
+
+        if let Some(nix_path) = &self.nix_path {
+            command.env("NIX_PATH", nix_path);
+        }
+
+        command
+    }
+
+    /// Options of the evaluation, to follow the (sub)command.
+    pub(crate) fn args(&self, working_dir: &camino::Utf8Path, example: &NixArgs) -> Vec<String> {
+        let mut args = self.eval_mode_args(working_dir);
+        args.extend(self.nix_args.to_args());
+        args.extend(example.to_args());
+        args
+    }
+
+    fn eval_mode_args(&self, working_dir: &camino::Utf8Path) -> Vec<String> {
+        let Some(eval_mode) = self.eval_mode else {
+            return vec![];
+        };
+
+        let (name, value) = eval_mode.option();
+        let option = ["--option", name, value].map(Into::into);
+
+        match eval_mode {
+            EvalMode::Pure => option.into(),
+            EvalMode::Restricted => option
+                .into_iter()
+                .chain(
+                    std::iter::once(working_dir)
+                        .chain(self.allowed_paths.iter().map(AsRef::as_ref))
+                        .flat_map(|path| ["-I".to_owned(), path.to_string()]),
+                )
+                .collect(),
+        }
+    }
+
+    /// `NIX_CONFIG` of a hermetic environment: just the options given to eelco.
+    fn nix_config(&self) -> String {
+        self.eval_mode
+            .map(EvalMode::option)
+            .into_iter()
+            .chain(
+                self.nix_args
+                    .options
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            )
+            .map(|(name, value)| format!("{name} = {value}\n"))
+            .collect()
+    }
+}
+
+/// Thrown by `builtins.getEnv` in expression examples evaluated with an evaluation mode,
+/// in which Nix would evaluate it to an empty string that no error reveals.
+const GET_ENV_THROW: &str = "eelco: builtins.getEnv is impure";
+
+/// `expression` in a scope in which `builtins.getEnv` throws,
+/// to be evaluated with an evaluation mode.
+pub(crate) fn shadow_get_env(expression: &str) -> String {
+    // the closing parenthesis on a line of its own, in case the expression ends with a comment
+    format!(
+        "(builtins: {expression}\n) \
+        (builtins // {{ getEnv = name: throw \"{GET_ENV_THROW}: ${{name}}\"; }})"
+    )
+}
+
+/// Nix errors that reveal that an example depends on its environment,
+/// with an explanation for each.
+const IMPURITIES: [(&str, &str); 9] = [
+    (
+        "attribute 'currentTime' missing",
+        "it depends on the time of evaluation (`builtins.currentTime`)",
+    ),
+    (
+        "attribute 'currentSystem' missing",
+        "it depends on the system of evaluation (`builtins.currentSystem`)",
+    ),
+    (
+        GET_ENV_THROW,
+        "it depends on an environment variable (`builtins.getEnv`)",
+    ),
+    (
+        "cannot look up '<",
+        "it depends on the search path (`NIX_PATH`), e.g. `<nixpkgs>`",
+    ),
+    (
+        "requires a 'sha256' argument",
+        "it fetches something without specifying its hash",
+    ),
+    ("access to URI", "it accesses a URI that is not allowed"),
+    (
+        "forbidden in pure evaluation mode",
+        "it accesses a path outside of the store",
+    ),
+    (
+        "forbidden in restricted mode",
+        "it accesses a path outside of the store, the search path and allowed paths",
+    ),
+    ("in pure evaluation mode", "it depends on its environment"),
+];
+
+/// Why an example is impure, if the output of Nix reveals that it is.
+pub(crate) fn impurity(output: &str) -> Option<&'static str> {
+    IMPURITIES
+        .iter()
+        .find(|(error, _explanation)| output.contains(error))
+        .map(|(_error, explanation)| *explanation)
+}
@@ -0,0 +1,71 @@
+/// Replacements applied in order to both the actual and the expected output of an example
+/// before they are compared, e.g. of the hashes of store paths.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Normalization(Vec<(regex::Regex, String)>);
+
+impl Normalization {
+    /// From `(pattern, replacement)` pairs, in the syntax of the `regex` crate,
+    /// where the replacement may refer to groups as in `$1`.
+    pub(crate) fn new<'a>(
+        rules: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> anyhow::Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|(pattern, replacement)| {
+                let regex = regex::Regex::new(pattern)
+                    .map_err(|error| anyhow::anyhow!("invalid pattern {pattern:?}: {error}"))?;
+                Ok((regex, replacement.to_owned()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self(rules))
+    }
+
+    pub(crate) fn apply(&self, text: &str) -> String {
+        self.0
+            .iter()
+            .fold(text.to_owned(), |text, (regex, replacement)| {
+                regex.replace_all(&text, replacement.as_str()).into_owned()
+            })
+    }
+}
+
+impl PartialEq for Normalization {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().zip(&other.0).all(
+                |((regex, replacement), (other_regex, other_replacement))| {
+                    regex.as_str() == other_regex.as_str() && replacement == other_replacement
+                },
+            )
+    }
+}
+
+impl Eq for Normalization {}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::Normalization;
+
+    #[test]
+    fn apply() {
+        let normalization = Normalization::new([
+            ("/nix/store/[0-9a-z]{32}-", "/nix/store/HASH-"),
+            (r"(\d+) ms", "$1 ms → N ms"),
+            ("N ms", "some time"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            normalization.apply("/nix/store/0c4b4ajbnkvjs8l9mb3r2ab3zydm6pka-hello took 12 ms"),
+            "/nix/store/HASH-hello took 12 ms → some time"
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(Normalization::new([("(", "")]).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use futures::{FutureExt, SinkExt, StreamExt};
+
+pub(crate) struct PrintlnDriver {
+    sender: futures::channel::mpsc::UnboundedSender<Printlned>,
+}
+
+pub(crate) struct Printlned;
+
+impl PrintlnDriver {
+    pub(crate) fn new() -> (Self, futures::stream::LocalBoxStream<'static, Printlned>) {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let driver = Self { sender };
+        (driver, receiver.boxed_local())
+    }
+
+    pub(crate) fn init(
+        mut self,
+        mut lines: futures::stream::LocalBoxStream<'static, String>,
+    ) -> futures::future::LocalBoxFuture<'static, ()> {
+        async move {
+            loop {
+                let line = futures::poll!(&mut lines.next());
+                if let std::task::Poll::Ready(Some(line)) = line {
+                    println!("{line}");
+                    self.sender.send(Printlned).await.unwrap();
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+        .boxed_local()
+    }
+}
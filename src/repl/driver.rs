@@ -7,7 +7,10 @@ use futures::{FutureExt, SinkExt, StreamExt};
 use itertools::Itertools;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::example_id::ExampleId;
+use crate::{
+    example_id::ExampleId,
+    nix_command::{NixArgs, NixCommandConfig},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, derive_more::Deref, derive_more::Display)]
 pub(crate) struct LFLine(String);
@@ -59,31 +62,49 @@ impl ReplQuery {
 
 #[derive(Debug)]
 pub(crate) enum ReplCommand {
-    Spawn(ExampleId),
+    Spawn(
+        ExampleId,
+        camino::Utf8PathBuf,
+        NixArgs,
+        Option<std::time::Duration>,
+    ),
     Query(ExampleId, ReplQuery),
     Kill(ExampleId),
 }
 
 #[derive(Debug)]
 pub(crate) enum ReplEvent {
-    Spawn(std::io::Result<ExampleId>),
+    Spawn(std::io::Result<(ExampleId, String)>),
     Query(ExampleId, ReplQuery, anyhow::Result<()>),
     Kill(anyhow::Result<ExampleId>),
     Read(ExampleId, u8),
+    /// the session outlived its timeout, and is yet to be killed
+    TimedOut(ExampleId),
     Error(std::io::Error),
 }
 
 pub(crate) struct ReplDriver {
-    sessions: std::collections::BTreeMap<ExampleId, (tokio::process::Child, tokio::fs::File)>,
+    sessions: std::collections::BTreeMap<ExampleId, Session>,
     sender: futures::channel::mpsc::UnboundedSender<ReplEvent>,
+    nix_command: NixCommandConfig,
+}
+
+struct Session {
+    child: tokio::process::Child,
+    output: tokio::fs::File,
+    /// `None` once reported as timed out
+    deadline: Option<std::time::Instant>,
 }
 
 impl ReplDriver {
-    pub(crate) fn new() -> (Self, futures::stream::LocalBoxStream<'static, ReplEvent>) {
+    pub(crate) fn new(
+        nix_command: NixCommandConfig,
+    ) -> (Self, futures::stream::LocalBoxStream<'static, ReplEvent>) {
         let (sender, receiver) = futures::channel::mpsc::unbounded::<ReplEvent>();
         let driver = Self {
             sessions: Default::default(),
             sender,
+            nix_command,
         };
         (driver, receiver.boxed_local())
     }
@@ -99,8 +120,19 @@ impl ReplDriver {
                     self.command(command).await;
                 }
 
-                for (id, (_child, child_output)) in self.sessions.iter_mut() {
-                    let byte = futures::poll!(std::pin::pin!(child_output.read_u8()));
+                let now = std::time::Instant::now();
+                for (id, session) in self.sessions.iter_mut() {
+                    if session.deadline.is_some_and(|deadline| deadline <= now) {
+                        session.deadline = None;
+                        self.sender
+                            .send(ReplEvent::TimedOut(id.clone()))
+                            .await
+                            .unwrap();
+                    }
+                }
+
+                for (id, session) in self.sessions.iter_mut() {
+                    let byte = futures::poll!(std::pin::pin!(session.output.read_u8()));
                     let std::task::Poll::Ready(byte) = byte else {
                         continue;
                     };
@@ -126,19 +158,38 @@ impl ReplDriver {
 
     async fn command(&mut self, repl_command: ReplCommand) {
         match repl_command {
-            ReplCommand::Spawn(id) => self.spawn(id).await,
+            ReplCommand::Spawn(id, working_dir, nix_args, timeout) => {
+                self.spawn(id, working_dir, nix_args, timeout).await
+            }
             ReplCommand::Query(id, query) => self.query(id, query).await,
             ReplCommand::Kill(id) => self.kill(id).await,
         }
     }
 
-    async fn spawn(&mut self, id: ExampleId) {
+    async fn spawn(
+        &mut self,
+        id: ExampleId,
+        working_dir: camino::Utf8PathBuf,
+        nix_args: NixArgs,
+        timeout: Option<std::time::Duration>,
+    ) {
         let (read_output, write_output) = nix::unistd::pipe().unwrap();
 
-        let child = tokio::process::Command::new(env!("NIX_CMD_PATH"))
-            // even though a single `--quiet` would normally disable the pre-prompt message
-            // (at the time of writing `Nix 2.21.1`), two seem to be necessary here.
+        let mut command = self.nix_command.command(env!("NIX_CMD_PATH"));
+        // even though a single `--quiet` would normally disable the pre-prompt message
+        // (at the time of writing `Nix 2.21.1`), two seem to be necessary here.
+        command
             .args(["repl", "--quiet", "--quiet"])
+            // `nix repl` is part of the newer CLI,
+            // which a hermetic environment does not enable through configuration
+            .args(["--extra-experimental-features", "nix-command"])
+            .args(self.nix_command.args(&working_dir, &nix_args))
+            .current_dir(working_dir)
+            // so that a session that is busy evaluating ends when killed
+            .kill_on_drop(true);
+        let command_line = format!("{:?}", command.as_std());
+
+        let child = command
             .stdin(Stdio::piped())
             .stdout(write_output.try_clone().unwrap())
             .stderr(write_output)
@@ -156,13 +207,21 @@ impl ReplDriver {
         };
 
         let read_output = unsafe { tokio::fs::File::from_raw_fd(read_output.into_raw_fd()) };
-        self.sessions.insert(id.clone(), (child, read_output));
-        self.sender.send(ReplEvent::Spawn(Ok(id))).await.unwrap();
+        let session = Session {
+            child,
+            output: read_output,
+            deadline: timeout.map(|timeout| std::time::Instant::now() + timeout),
+        };
+        self.sessions.insert(id.clone(), session);
+        self.sender
+            .send(ReplEvent::Spawn(Ok((id, command_line))))
+            .await
+            .unwrap();
     }
 
     async fn query(&mut self, id: ExampleId, query: ReplQuery) {
         let child = match self.sessions.get_mut(&id) {
-            Some((child, _file)) => child,
+            Some(session) => &mut session.child,
             None => {
                 let error = anyhow::anyhow!("no pty for {id:?}");
                 self.sender
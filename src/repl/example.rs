@@ -1,22 +1,94 @@
 use anyhow::bail;
+use itertools::Itertools;
 
-use crate::{app::state::repl_state::ExpectedResult, example_id::ExampleId};
+use crate::{
+    app::state::repl_state::ExpectedResult, example_id::ExampleId, nix_command::NixArgs,
+    normalization::Normalization,
+};
 
 use super::driver::ReplQuery;
 
+/// One repl session, of one code block or of all the blocks of a named session.
 #[derive(Debug, Clone)]
 pub(crate) struct ReplExample {
+    /// that of the first block
+    pub(crate) id: ExampleId,
+    pub(crate) session: Option<String>,
+    /// of the `nix repl` process
+    pub(crate) working_dir: camino::Utf8PathBuf,
+    /// those of the first block
+    pub(crate) nix_args: NixArgs,
+    /// that of the first block, after which the session is killed and fails
+    pub(crate) timeout: Option<std::time::Duration>,
+    /// applied to the results and to what is expected of them
+    pub(crate) normalization: Normalization,
+    pub(crate) blocks: Vec<ReplBlock>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ReplBlock {
     pub(crate) id: ExampleId,
     pub(crate) entries: ReplExampleEntries,
 }
 
 impl ReplExample {
-    pub(crate) fn try_new(id: ExampleId, contents: String) -> anyhow::Result<Self> {
+    pub(crate) fn try_new(
+        id: ExampleId,
+        session: Option<String>,
+        working_dir: camino::Utf8PathBuf,
+        nix_args: NixArgs,
+        timeout: Option<std::time::Duration>,
+        normalization: Normalization,
+        contents: String,
+    ) -> anyhow::Result<Self> {
         Ok(Self {
-            id,
-            entries: contents.parse()?,
+            id: id.clone(),
+            session,
+            working_dir,
+            nix_args,
+            timeout,
+            normalization,
+            blocks: vec![ReplBlock {
+                id,
+                entries: contents.parse()?,
+            }],
         })
     }
+
+    pub(crate) fn ids(&self) -> impl Iterator<Item = &ExampleId> {
+        self.blocks.iter().map(|block| &block.id)
+    }
+
+    /// The entries of all blocks, each with the id of its block.
+    pub(crate) fn entries(&self) -> Vec<(ExampleId, ReplEntry)> {
+        self.blocks
+            .iter()
+            .flat_map(|block| {
+                block
+                    .entries
+                    .0
+                    .iter()
+                    .map(|entry| (block.id.clone(), entry.clone()))
+            })
+            .collect()
+    }
+
+    /// Precedes the entries with one that adds the bindings of the prelude to the scope.
+    /// The repl reads a line at a time, so the prelude is joined into one,
+    /// without the lines that are comments.
+    pub(crate) fn with_prelude(mut self, prelude: &str) -> anyhow::Result<Self> {
+        let bindings = prelude
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .join(" ");
+        let query = ReplQuery::new(format!(":a rec {{ {bindings} }}\n").try_into()?);
+        let Some(block) = self.blocks.first_mut() else {
+            bail!("session {} without blocks", self.id);
+        };
+        block.entries.0.insert(0, ReplEntry::unchecked(query));
+        Ok(self)
+    }
 }
 
 #[derive(Debug, Clone, derive_more::IntoIterator)]
@@ -51,14 +123,22 @@ impl std::str::FromStr for ReplExampleEntries {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ReplEntry {
     pub(crate) query: ReplQuery,
-    pub(crate) expected_result: ExpectedResult,
+    /// `None` if the result is not checked
+    pub(crate) expected_result: Option<ExpectedResult>,
 }
 
 impl ReplEntry {
     pub(crate) fn new(query: ReplQuery, expected_result: ExpectedResult) -> Self {
         Self {
             query,
-            expected_result,
+            expected_result: Some(expected_result),
+        }
+    }
+
+    pub(crate) fn unchecked(query: ReplQuery) -> Self {
+        Self {
+            query,
+            expected_result: None,
         }
     }
 }
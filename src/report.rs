@@ -0,0 +1,337 @@
+pub(crate) mod html;
+
+use itertools::Itertools;
+
+use crate::{example_id::ExampleId, repl::driver::ReplQuery};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Format {
+    /// `PASS:` lines on stderr, with a progress display if stderr is a terminal
+    #[default]
+    Human,
+    /// newline-delimited JSON events on stdout
+    Json,
+    /// `PASS:` lines on stderr, workflow command annotations for failures on stdout
+    /// and a summary appended to `$GITHUB_STEP_SUMMARY`
+    Github,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReportConfig {
+    pub(crate) format: Format,
+    pub(crate) verbosity: Verbosity,
+    pub(crate) progress: bool,
+    pub(crate) stream: bool,
+    pub(crate) github_step_summary: Option<camino::Utf8PathBuf>,
+    pub(crate) targets: Vec<ReportTarget>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Verbosity {
+    /// only failures and the summary
+    Quiet,
+    #[default]
+    Normal,
+    /// Nix command lines and repl queries as well
+    Verbose,
+    /// raw Nix output as well
+    VeryVerbose,
+}
+
+impl Verbosity {
+    pub(crate) fn new(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, _) => Self::VeryVerbose,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReportTarget {
+    Html(camino::Utf8PathBuf),
+}
+
+impl std::str::FromStr for ReportTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, path)) = s.split_once('=') else {
+            anyhow::bail!("expected `KIND=PATH`, got {s:?}");
+        };
+
+        match kind {
+            "html" => Ok(Self::Html(path.into())),
+            _ => anyhow::bail!("unknown report kind {kind:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ExampleResult {
+    pub(crate) status: ExampleStatus,
+    pub(crate) duration: std::time::Duration,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ExampleStatus {
+    Passed,
+    Failed(Failure),
+    Skipped,
+}
+
+impl ExampleStatus {
+    pub(crate) fn diagnostics(&self) -> Option<&str> {
+        match self {
+            Self::Failed(failure) => Some(&failure.diagnostics),
+            Self::Passed | Self::Skipped => None,
+        }
+    }
+}
+
+/// Why an example failed, in parts for reports that present them separately.
+#[derive(Debug, Clone)]
+pub(crate) struct Failure {
+    pub(crate) kind: FailureKind,
+    /// all of it, as text
+    pub(crate) diagnostics: String,
+    /// that precedes the rest, e.g. why the example is impure
+    pub(crate) summary: Option<String>,
+    /// of the output of the example that differs from what is expected of it
+    pub(crate) mismatches: Vec<Mismatch>,
+    /// of Nix, if it failed
+    pub(crate) nix_stderr: Option<String>,
+}
+
+impl Failure {
+    pub(crate) fn new(diagnostics: String) -> Self {
+        Self {
+            kind: FailureKind::Failed,
+            diagnostics,
+            summary: None,
+            mismatches: Vec::new(),
+            nix_stderr: None,
+        }
+    }
+
+    pub(crate) fn mismatches(mismatches: Vec<Mismatch>) -> Self {
+        let diagnostics = mismatches.iter().map(Mismatch::diagnostics).join("\n\n");
+        Self {
+            mismatches,
+            ..Self::new(diagnostics)
+        }
+    }
+}
+
+/// An output of an example, such as its printed result, that differs from what is expected of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Mismatch {
+    /// which output, e.g. `` `bin/hello` of the output ``, unless it is the result
+    pub(crate) subject: Option<String>,
+    /// of the code blocks of the diagnostics
+    pub(crate) language: &'static str,
+    pub(crate) actual: String,
+    pub(crate) expected: String,
+}
+
+impl Mismatch {
+    pub(crate) fn diagnostics(&self) -> String {
+        let Self {
+            subject,
+            language,
+            actual,
+            expected,
+        } = self;
+        let subject = subject
+            .as_ref()
+            .map(|subject| format!(" {subject}"))
+            .unwrap_or_default();
+
+        indoc::formatdoc! {"
+            Actual{subject}:
+
+            ```{language}
+            {actual}
+            ```
+
+            Expected{subject}:
+
+            ```{language}
+            {expected}
+            ```"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FailureKind {
+    Failed,
+    /// denied access to its environment by pure or restricted evaluation
+    Impure,
+}
+
+impl FailureKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Failed => "failed",
+            Self::Impure => "impure",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ReportEvent {
+    RunStarted,
+    ExampleDiscovered(ExampleId),
+    ExampleStarted(ExampleId),
+    NixSpawned(ExampleId, String),
+    ReplQuerySent(ExampleId, ReplQuery),
+    ChildOutput(ExampleId, String),
+    ExamplePassed(ExampleId, std::time::Duration),
+    ExampleFailed(ExampleId, std::time::Duration, FailureKind, String),
+    ExampleSkipped(ExampleId),
+    RunFinished(RunSummary),
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunSummary {
+    pub(crate) passed: usize,
+    pub(crate) failed: usize,
+    pub(crate) skipped: usize,
+    pub(crate) duration: std::time::Duration,
+}
+
+impl RunSummary {
+    pub(crate) fn to_human(&self) -> String {
+        let Self {
+            passed,
+            failed,
+            skipped,
+            duration,
+        } = self;
+
+        format!(
+            "{passed} passed, {failed} failed, {skipped} skipped in {} ms",
+            duration.as_millis()
+        )
+    }
+}
+
+impl ReportEvent {
+    pub(crate) fn to_json(&self) -> Option<serde_json::Value> {
+        let json = match self {
+            Self::RunStarted => serde_json::json!({ "event": "run_started" }),
+            Self::ExampleDiscovered(id) => Self::example_json("example_discovered", id),
+            Self::ExampleStarted(id) => Self::example_json("example_started", id),
+            Self::ReplQuerySent(id, query) => {
+                let mut json = Self::example_json("repl_query_sent", id);
+                json["query"] = query.as_str().into();
+                json
+            }
+            Self::ExamplePassed(id, duration) => {
+                let mut json = Self::example_json("example_passed", id);
+                json["duration_ms"] = millis(duration).into();
+                json
+            }
+            Self::ExampleFailed(id, duration, kind, diagnostics) => {
+                let mut json = Self::example_json("example_failed", id);
+                json["duration_ms"] = millis(duration).into();
+                json["kind"] = kind.as_str().into();
+                json["diagnostics"] = diagnostics.as_str().into();
+                json
+            }
+            Self::ExampleSkipped(id) => Self::example_json("example_skipped", id),
+            Self::RunFinished(summary) => serde_json::json!({
+                "event": "run_finished",
+                "passed": summary.passed,
+                "failed": summary.failed,
+                "skipped": summary.skipped,
+                "duration_ms": millis(&summary.duration),
+            }),
+            Self::NixSpawned(..) | Self::ChildOutput(..) => return None,
+        };
+
+        Some(json)
+    }
+
+    fn example_json(event: &str, id: &ExampleId) -> serde_json::Value {
+        serde_json::json!({
+            "event": event,
+            "id": id.to_string(),
+            "file": id.source_path().as_str(),
+            "line": id.line(),
+        })
+    }
+}
+
+fn millis(duration: &std::time::Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+pub(crate) fn github_error_annotation(
+    id: &ExampleId,
+    kind: FailureKind,
+    diagnostics: &str,
+) -> String {
+    let title = match kind {
+        FailureKind::Failed => "Nix example failed",
+        FailureKind::Impure => "Nix example impure",
+    };
+
+    format!(
+        "::error file={},line={},title={}::{}",
+        escape_workflow_command_property(id.source_path().as_str()),
+        id.line(),
+        escape_workflow_command_property(title),
+        escape_workflow_command_data(diagnostics),
+    )
+}
+
+pub(crate) fn github_step_summary(
+    summary: &RunSummary,
+    results: &std::collections::BTreeMap<ExampleId, ExampleResult>,
+) -> String {
+    let RunSummary {
+        passed,
+        failed,
+        skipped,
+        ..
+    } = summary;
+
+    let mut markdown = indoc::formatdoc! {"
+        ## eelco
+
+        **{passed}** passed, **{failed}** failed, **{skipped}** skipped
+    "};
+
+    results.iter().for_each(|(id, result)| {
+        let Some(diagnostics) = result.status.diagnostics() else {
+            return;
+        };
+        let diagnostics = diagnostics.trim_end();
+        markdown.push_str(&indoc::formatdoc! {"
+
+            ### `{id}`
+
+            ````
+            {diagnostics}
+            ````
+        "});
+    });
+
+    markdown
+}
+
+fn escape_workflow_command_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_workflow_command_property(property: &str) -> String {
+    escape_workflow_command_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
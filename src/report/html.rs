@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+
+use crate::{
+    example_id::ExampleId,
+    examples::{frontmatter, Document, SourceFormat},
+};
+
+use super::{ExampleResult, ExampleStatus, Failure, FailureKind, Mismatch, RunSummary};
+
+const STYLE: &str = "
+    body { font-family: sans-serif; max-width: 60em; margin: auto; padding: 1em; }
+    pre { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+    .example { border-left: 0.3em solid; padding: 0.2em 0.5em; margin-top: 1em; }
+    .example.passed { border-color: #2da44e; }
+    .example.failed { border-color: #cf222e; background: #ffebe9; }
+    .example.skipped { border-color: #9a6700; }
+    .status { font-weight: bold; }
+    .diff .added { background: #dafbe1; }
+    .diff .removed { background: #ffebe9; }
+    .diff .hunk { color: #6e7781; }
+";
+
+pub(crate) fn render(
+    documents: &[Document],
+    results: &BTreeMap<ExampleId, ExampleResult>,
+    summary: &RunSummary,
+) -> String {
+    let RunSummary {
+        passed,
+        failed,
+        skipped,
+        duration,
+    } = summary;
+
+    let navigation = documents
+        .iter()
+        .enumerate()
+        .map(|(index, document)| {
+            format!(
+                "<li><a href=\"#document-{index}\"><code>{}</code></a></li>",
+                escape(document.path.as_str())
+            )
+        })
+        .join("\n");
+
+    let sections = documents
+        .iter()
+        .enumerate()
+        .map(|(index, document)| {
+            format!(
+                "<section id=\"document-{index}\">\n<h2><code>{}</code></h2>\n{}</section>",
+                escape(document.path.as_str()),
+                render_document(document, results),
+            )
+        })
+        .join("\n");
+
+    indoc::formatdoc! {"
+        <!DOCTYPE html>
+        <html lang=\"en\">
+        <head>
+        <meta charset=\"utf-8\">
+        <title>eelco report</title>
+        <style>{STYLE}</style>
+        </head>
+        <body>
+        <h1>eelco report</h1>
+        <p>{passed} passed, {failed} failed, {skipped} skipped in {duration} ms</p>
+        <ul>
+        {navigation}
+        </ul>
+        {sections}
+        </body>
+        </html>
+        ",
+        duration = duration.as_millis(),
+    }
+}
+
+fn render_document(document: &Document, results: &BTreeMap<ExampleId, ExampleResult>) -> String {
+    let results_by_line = results
+        .iter()
+        .filter(|(id, _result)| id.source_path() == document.path)
+        .map(|(id, result)| (id.line(), (id, result)))
+        .collect::<BTreeMap<_, _>>();
+
+    match document.format() {
+        SourceFormat::Markdown => render_markdown(document, &results_by_line),
+        _ => render_source(document, &results_by_line),
+    }
+}
+
+/// Renders the markdown of a document
+/// with the status of each example inserted right above it.
+fn render_markdown(
+    document: &Document,
+    results_by_line: &BTreeMap<usize, (&ExampleId, &ExampleResult)>,
+) -> String {
+    use comrak::nodes::{Ast, NodeHtmlBlock, NodeValue};
+
+    let contents = frontmatter::blank(&document.contents);
+    let arena = comrak::Arena::new();
+    let mut options = comrak::ComrakOptions::default();
+    let root = comrak::parse_document(&arena, &contents, &options);
+
+    root.descendants()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .for_each(|node| {
+            let (value, start, end) = {
+                let ast = node.data.borrow();
+                (ast.value.clone(), ast.sourcepos.start, ast.sourcepos.end)
+            };
+
+            match value {
+                // raw HTML of the document is shown as text,
+                // except for comments such as directives, which are not shown at all
+                NodeValue::HtmlBlock(NodeHtmlBlock { literal, .. })
+                | NodeValue::HtmlInline(literal)
+                    if literal.trim_start().starts_with("<!--") =>
+                {
+                    node.detach();
+                }
+                NodeValue::HtmlBlock(NodeHtmlBlock { literal, .. })
+                | NodeValue::HtmlInline(literal) => {
+                    node.data.borrow_mut().value = NodeValue::Text(literal);
+                }
+                // the results of annotated expressions are those of lines within the block
+                NodeValue::CodeBlock(_) => results_by_line.range(start.line..=end.line).for_each(
+                    |(_line, (id, result))| {
+                        let status = NodeValue::HtmlBlock(NodeHtmlBlock {
+                            block_type: 6,
+                            literal: render_status(id, result),
+                        });
+                        let status = Ast::new(status, start);
+                        node.insert_before(arena.alloc(comrak::arena_tree::Node::new(
+                            std::cell::RefCell::new(status),
+                        )));
+                    },
+                ),
+                _ => {}
+            }
+        });
+
+    // only the statuses are raw HTML by now
+    options.render.unsafe_ = true;
+    let mut html = Vec::new();
+    match comrak::format_html(root, &options, &mut html) {
+        Ok(()) => String::from_utf8_lossy(&html).into_owned(),
+        Err(error) => format!("<p>could not render: {}</p>", escape(&error.to_string())),
+    }
+}
+
+/// Renders the status of each example followed by the verbatim source of the document.
+fn render_source(
+    document: &Document,
+    results_by_line: &BTreeMap<usize, (&ExampleId, &ExampleResult)>,
+) -> String {
+    let statuses = results_by_line
+        .values()
+        .map(|(id, result)| render_status(id, result))
+        .join("\n");
+
+    format!(
+        "{statuses}\n<pre><code>{}</code></pre>\n",
+        escape(&document.contents)
+    )
+}
+
+fn render_status(id: &ExampleId, result: &ExampleResult) -> String {
+    let (class, label, details) = match &result.status {
+        ExampleStatus::Passed => ("passed", "PASS", String::new()),
+        ExampleStatus::Failed(failure) => match failure.kind {
+            FailureKind::Failed => ("failed", "FAIL", render_failure(failure)),
+            FailureKind::Impure => ("failed impure", "IMPURE", render_failure(failure)),
+        },
+        ExampleStatus::Skipped => ("skipped", "SKIP", String::new()),
+    };
+
+    format!(
+        "<div class=\"example {class}\"><span class=\"status\">{label}</span> <code>{}</code> {} ms{details}</div>",
+        escape(&id.to_string()),
+        result.duration.as_millis(),
+    )
+}
+
+/// Renders each mismatch as a diff and the stderr of Nix apart,
+/// or else the diagnostics as they are.
+fn render_failure(failure: &Failure) -> String {
+    let Failure {
+        diagnostics,
+        summary,
+        mismatches,
+        nix_stderr,
+        ..
+    } = failure;
+
+    if mismatches.is_empty() && nix_stderr.is_none() {
+        return format!("<pre>{}</pre>", escape(diagnostics));
+    }
+
+    let summary = summary
+        .iter()
+        .map(|summary| format!("<p>{}</p>", escape(summary)));
+    let diffs = mismatches.iter().map(render_diff);
+    let nix_stderr = nix_stderr.iter().map(|nix_stderr| {
+        format!(
+            "<p>Nix stderr:</p>\n<pre class=\"stderr\">{}</pre>",
+            escape(nix_stderr)
+        )
+    });
+
+    summary.chain(diffs).chain(nix_stderr).join("\n")
+}
+
+/// Renders a unified diff from the expected to the actual output.
+fn render_diff(mismatch: &Mismatch) -> String {
+    let Mismatch {
+        subject,
+        actual,
+        expected,
+        ..
+    } = mismatch;
+    let (expected, actual) = (format!("{expected}\n"), format!("{actual}\n"));
+    let header = |side: &str| match subject {
+        Some(subject) => format!("{side} {subject}"),
+        None => side.to_owned(),
+    };
+
+    let diff = similar::TextDiff::from_lines(&expected, &actual)
+        .unified_diff()
+        .header(&header("expected"), &header("actual"))
+        .to_string();
+
+    let lines = diff
+        .lines()
+        .map(|line| {
+            let class = match line.chars().next() {
+                _ if line.starts_with("---") || line.starts_with("+++") => "header",
+                Some('+') => "added",
+                Some('-') => "removed",
+                Some('@') => "hunk",
+                _ => return escape(line),
+            };
+            format!("<span class=\"{class}\">{}</span>", escape(line))
+        })
+        .join("\n");
+
+    format!("<pre class=\"diff\">{lines}</pre>")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{render_diff, Mismatch};
+
+    #[test]
+    fn diff() {
+        let mismatch = Mismatch {
+            subject: Some("`out` of the output".to_owned()),
+            language: "",
+            actual: "1\n<2>".to_owned(),
+            expected: "1\n3".to_owned(),
+        };
+
+        assert_eq!(
+            render_diff(&mismatch),
+            [
+                "<pre class=\"diff\"><span class=\"header\">--- expected `out` of the output</span>",
+                "<span class=\"header\">+++ actual `out` of the output</span>",
+                "<span class=\"hunk\">@@ -1,2 +1,2 @@</span>",
+                " 1",
+                "<span class=\"removed\">-3</span>",
+                "<span class=\"added\">+&lt;2&gt;</span></pre>",
+            ]
+            .join("\n")
+        );
+    }
+}
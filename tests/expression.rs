@@ -3,12 +3,12 @@ mod util;
 use std::os::unix::fs::PermissionsExt;
 
 use assert_fs::fixture::FileWriteStr;
-use indoc::indoc;
+use indoc::{formatdoc, indoc};
 use predicates::{
     prelude::PredicateBooleanExt,
     str::{contains, starts_with},
 };
-use util::with_eelco;
+use util::{eelco, with_eelco};
 
 #[test]
 fn assertion_fail() {
@@ -65,13 +65,639 @@ fn io_error() {
         perms.set_mode(0o000);
         std::fs::set_permissions(&file, perms).unwrap();
 
-        let mut eelco = assert_cmd::Command::cargo_bin("eelco").unwrap();
+        eelco()
+            .arg(file.as_os_str())
+            .assert()
+            .failure()
+            .stderr(predicates::str::starts_with("Error: "));
+    });
+}
+
+#[test]
+fn annotations() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix
+            builtins.toString 1
+            => "1"
+            builtins.length [ 1 2 ]
+            => 3
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().failure().stderr(
+            starts_with(format!("PASS: {file_path}:2\n"))
+                .and(contains(format!("Error: {file_path}:4\n")))
+                .and(contains("expected 3 but got 2")),
+        );
+    });
+}
+
+#[test]
+fn annotations_context() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix
+            toString 1
+            => "1"
+            ```
+        "#})
+            .unwrap();
+
+        eelco
+            .args(["--annotation-context", "with builtins;"])
+            .assert()
+            .success();
+    });
+}
+
+#[test]
+fn hidden_lines() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            #~ let n = 2; in
+            assert n == 2; null
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn prelude() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix prelude
+            n = 2;
+            ```
+
+            ```nix
+            assert n == 2; null
+            ```
+
+            ```nix
+            n + 1
+            => 3
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:5
+            PASS: {file_path}:10
+        "});
+    });
+}
+
+#[test]
+fn working_dir() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            assert import ./two.nix == 2; null
+            ```
+        "})
+            .unwrap();
 
-        eelco.arg(file.as_os_str());
+        let dir = file.path().parent().unwrap();
+        std::fs::write(dir.join("two.nix"), "2").unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .current_dir("/")
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn base_dir() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            assert import ./two.nix == 2; null
+            ```
+        "})
+            .unwrap();
+
+        let base_dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(base_dir.path().join("two.nix"), "2").unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--base-dir")
+            .arg(base_dir.path())
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn fixtures() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            Create `example/default.nix`:
+
+            ```nix file=example/default.nix
+            { two = 2; }
+            ```
+
+            ```nix
+            assert (import ./example).two == 2; null
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:7\n"));
+    });
+}
+
+#[test]
+fn fixtures_overlay() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix file=three.nix
+            import ./two.nix + 1
+            ```
+
+            ```nix
+            assert import ./three.nix == 3; null
+            ```
+        "})
+            .unwrap();
+
+        let base_dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(base_dir.path().join("two.nix"), "2").unwrap();
+        std::fs::write(base_dir.path().join("three.nix"), "0").unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--base-dir")
+            .arg(base_dir.path())
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:5\n"));
+    });
+}
+
+#[test]
+fn fixture_outside() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix file=../default.nix
+            null
+            ```
+        "})
+            .unwrap();
 
         eelco
             .assert()
             .failure()
-            .stderr(predicates::str::starts_with("Error: "));
+            .stderr(contains("must be relative and without `..`"));
+    });
+}
+
+#[test]
+fn hermetic() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix
+            assert builtins.getEnv "EELCO_NOT_PASSED" == "";
+            assert builtins.getEnv "EELCO_PASSED" == "1";
+            assert builtins.getEnv "NIX_PATH" == "example=/example";
+            null
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .env("EELCO_NOT_PASSED", "1")
+            .env("EELCO_PASSED", "1")
+            .args(["--hermetic", "--pass-env", "EELCO_PASSED"])
+            .args(["--nix-path", "example=/example"])
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn hermetic_config() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            builtins.currentTime
+            ```
+        "})
+            .unwrap();
+
+        let config_dir = assert_fs::TempDir::new().unwrap();
+        let config_file = config_dir.path().join("nix.conf");
+        std::fs::write(&config_file, "pure-eval = true\n").unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .env("NIX_CONFIG", "pure-eval = true")
+            .env("NIX_CONF_DIR", config_dir.path())
+            .env("NIX_USER_CONF_FILES", &config_file)
+            .arg("--hermetic")
+            .args(["--pass-env", "NIX_CONFIG"])
+            .args(["--pass-env", "NIX_CONF_DIR"])
+            .args(["--pass-env", "NIX_USER_CONF_FILES"])
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn impure() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            builtins.currentTime
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .args(["--eval-mode", "pure"])
+            .assert()
+            .failure()
+            .stderr(
+                starts_with(formatdoc! {"
+                    Error: {file_path}:1
+
+                    impure example: it depends on the time of evaluation (`builtins.currentTime`)
+                "})
+                .and(contains("attribute 'currentTime' missing")),
+            );
+    });
+}
+
+#[test]
+fn impure_get_env() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix
+            builtins.getEnv "HOME"
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .args(["--eval-mode", "restricted"])
+            .assert()
+            .failure()
+            .stderr(
+                starts_with(formatdoc! {"
+                    Error: {file_path}:1
+
+                    impure example: it depends on an environment variable (`builtins.getEnv`)
+                "})
+                .and(contains("eelco: builtins.getEnv is impure: HOME")),
+            );
+    });
+}
+
+#[test]
+fn nix_args() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix argstr=greeting=hello
+            { greeting, two }: assert greeting == "hello"; assert two == 2; null
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .args(["--arg", "two=1 + 1"])
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn strict() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix strict
+            { a = assert false; null; }
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .failure()
+            .stderr(starts_with(format!("Error: {file_path}:1")).and(contains("assertion")));
+    });
+}
+
+#[test]
+fn expected_json() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix
+            { b = [ 1 2 ]; a = "one"; }
+            ```
+
+            ```json expected
+            { "a": "one", "b": [1, 2] }
+            ```
+
+            ```nix
+            { a = 2; }
+            ```
+
+            ```json expected
+            { "a": 1 }
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().failure().stderr(formatdoc! {r#"
+            PASS: {file_path}:1
+            Error: {file_path}:9
+
+            Actual:
+
+            ```json
+            {{
+              "a": 2
+            }}
+            ```
+
+            Expected:
+
+            ```json
+            {{
+              "a": 1
+            }}
+            ```
+        "#});
+    });
+}
+
+#[test]
+fn nix_eval_backend() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```nix backend=nix-eval apply="attrs: attrs.a"
+            { a = [ 1 ]; }
+            ```
+
+            ```json expected
+            [1]
+            ```
+
+            ```nix backend=nix-eval raw
+            "no quotes"
+            ```
+
+            ```text expected
+            no quotes
+            ```
+
+            ```nix backend=nix-eval raw
+            "two\nlines"
+            ```
+
+            ```text expected
+            two
+            words
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().failure().stderr(formatdoc! {"
+            PASS: {file_path}:1
+            PASS: {file_path}:9
+            Error: {file_path}:17
+
+            Actual:
+
+            ```
+            two
+            lines
+            ```
+
+            Expected:
+
+            ```
+            two
+            words
+            ```
+        "});
+    });
+}
+
+#[test]
+fn flake() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix file=flake.nix
+            { outputs = { self }: { two = 1 + 1; }; }
+            ```
+
+            ```nix backend=nix-eval flake=.
+            two
+            ```
+
+            ```text expected
+            2
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:5\n"));
+    });
+}
+
+#[test]
+fn build() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```text file=greeting.txt
+            hello
+            ```
+
+            ```nix build
+            derivation {
+              name = "greeting";
+              system = "builtin";
+              builder = "builtin:fetchurl";
+              url = "file://${builtins.toString ./greeting.txt}";
+              outputHashMode = "flat";
+              outputHashAlgo = "sha256";
+              outputHash = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+            }
+            ```
+
+            ```text output
+            hello
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:5\n"));
+    });
+}
+
+#[test]
+fn build_with_json() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ```text file=greeting.txt
+            hello
+            ```
+
+            ```nix build
+            derivation {
+              name = "greeting";
+              system = "builtin";
+              builder = "builtin:fetchurl";
+              url = "file://${builtins.toString ./greeting.txt}";
+              outputHashMode = "flat";
+              outputHashAlgo = "sha256";
+              outputHash = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+            }
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--json")
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:5\n"));
+    });
+}
+
+#[test]
+fn expectations_of_other_languages() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```sh
+            echo hello
+            ```
+
+            ```text output
+            hello
+            ```
+
+            ```nix
+            null
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:9\n"));
+    });
+}
+
+#[test]
+fn timeout() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix timeout=1s
+            let sum = n: builtins.foldl' builtins.add 0 (builtins.genList (x: x) n); in
+            builtins.foldl' (acc: _: acc + sum 100000) 0 (builtins.genList (x: x) 100000)
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .failure()
+            .stderr(format!("Error: {file_path}:1\n\ntimed out after 1s\n"));
+    });
+}
+
+#[test]
+fn normalize() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            ---
+            eelco:
+              normalize:
+                - pattern: "[0-9]+ ms"
+                  replacement: "N ms"
+            ---
+
+            ```nix
+            "took ${toString (builtins.length [ 1 2 3 ])} ms"
+            ```
+
+            ```text expected
+            "took 12 ms"
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:8\n"));
     });
 }
@@ -0,0 +1,183 @@
+mod util;
+
+use assert_fs::{fixture::FileWriteStr, NamedTempFile};
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+use util::with_eelco;
+
+fn json_events(stdout: &[u8]) -> Vec<serde_json::Value> {
+    std::str::from_utf8(stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn json_pass() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            null
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        let output = eelco.args(["--format", "json"]).assert().success();
+        let events = json_events(&output.get_output().stdout);
+        let names: Vec<&str> = events
+            .iter()
+            .map(|event| event["event"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                "run_started",
+                "example_discovered",
+                "example_started",
+                "example_passed",
+                "run_finished",
+            ]
+        );
+        assert_eq!(events[1]["id"], format!("{file_path}:1"));
+        assert_eq!(events[1]["file"], file_path);
+        assert_eq!(events[1]["line"], 1);
+        assert_eq!(events[4]["passed"], 1);
+    });
+}
+
+#[test]
+fn json_failed_and_skipped() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix-repl
+            nix-repl> 1 + 1
+            3
+
+            ```
+
+            ```nix skip
+            null
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        let output = eelco.args(["--format", "json"]).assert().failure();
+        let events = json_events(&output.get_output().stdout);
+
+        let failed = events
+            .iter()
+            .find(|event| event["event"] == "example_failed")
+            .unwrap();
+        assert_eq!(failed["id"], format!("{file_path}:1"));
+        assert_eq!(failed["kind"], "failed");
+        assert!(failed["diagnostics"].as_str().unwrap().contains("Actual:"));
+
+        let skipped = events
+            .iter()
+            .find(|event| event["event"] == "example_skipped")
+            .unwrap();
+        assert_eq!(skipped["id"], format!("{file_path}:7"));
+
+        assert!(events
+            .iter()
+            .any(|event| event["event"] == "repl_query_sent" && event["query"] == "1 + 1\n"));
+
+        let finished = events.last().unwrap();
+        assert_eq!(finished["event"], "run_finished");
+        assert_eq!(finished["failed"], 1);
+        assert_eq!(finished["skipped"], 1);
+    });
+}
+
+#[test]
+fn github_annotations_and_step_summary() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            null
+            ```
+
+            ```nix-repl
+            nix-repl> 1 + 1
+            3
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+        let step_summary = NamedTempFile::new("step-summary.md").unwrap();
+
+        eelco
+            .env("GITHUB_ACTIONS", "true")
+            .env("GITHUB_STEP_SUMMARY", step_summary.path())
+            .assert()
+            .failure()
+            .stdout(predicates::str::starts_with(format!(
+                "::error file={file_path},line=5,title=Nix example failed::Actual:%0A%0A```%0A2%0A```"
+            )))
+            .stderr(predicates::str::contains(format!("PASS: {file_path}:1\n")));
+
+        let step_summary = std::fs::read_to_string(step_summary.path()).unwrap();
+        assert!(step_summary.contains("**1** passed, **1** failed, **0** skipped"));
+        assert!(step_summary.contains(&format!("### `{file_path}:5`")));
+    });
+}
+
+#[test]
+fn html_report() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            # Title
+
+            ```nix
+            null
+            ```
+
+            ```nix-repl
+            nix-repl> 1 + 1
+            3
+
+            ```
+
+            > ```nix
+            > null
+            > ```
+
+            <script>alert(1)</script>
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+        let report = NamedTempFile::new("report.html").unwrap();
+
+        eelco
+            .arg("--report")
+            .arg(format!("html={}", report.path().to_str().unwrap()))
+            .assert()
+            .failure();
+
+        let report = std::fs::read_to_string(report.path()).unwrap();
+        assert!(report.contains("2 passed, 1 failed, 0 skipped"));
+        assert!(report.contains("<h1>Title</h1>"));
+        assert!(report.contains(&format!(
+            "<div class=\"example passed\"><span class=\"status\">PASS</span> <code>{file_path}:3</code>"
+        )));
+        assert!(report.contains(&format!(
+            "<div class=\"example failed\"><span class=\"status\">FAIL</span> <code>{file_path}:7</code>"
+        )));
+        assert!(report
+            .contains("<span class=\"removed\">-3</span>\n<span class=\"added\">+2</span></pre>"));
+        assert!(report.contains(&format!(
+            "<blockquote>\n<div class=\"example passed\"><span class=\"status\">PASS</span> <code>{file_path}:13</code>"
+        )));
+        assert!(!report.contains("<script>"));
+        assert!(report.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    });
+}
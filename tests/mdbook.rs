@@ -0,0 +1,144 @@
+mod util;
+
+use indoc::indoc;
+
+use util::eelco;
+
+fn eelco_mdbook() -> assert_cmd::Command {
+    let mut command = eelco();
+    command.arg("mdbook");
+    command
+}
+
+fn preprocessor_input(strip: bool, content: &str) -> String {
+    serde_json::json!([
+        {
+            "root": "/book",
+            "config": {
+                "book": { "src": "src" },
+                "preprocessor": { "eelco": { "strip": strip } },
+            },
+            "renderer": "html",
+            "mdbook_version": "0.4.35",
+        },
+        {
+            "sections": [
+                {
+                    "Chapter": {
+                        "name": "Chapter",
+                        "content": content,
+                        "number": [1],
+                        "sub_items": [],
+                        "path": "chapter.md",
+                        "source_path": "chapter.md",
+                        "parent_names": [],
+                    },
+                },
+            ],
+            "__non_exhaustive": null,
+        },
+    ])
+    .to_string()
+}
+
+fn output_content(output: &[u8]) -> String {
+    let book: serde_json::Value = serde_json::from_slice(output).unwrap();
+    book["sections"][0]["Chapter"]["content"]
+        .as_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn supports() {
+    eelco_mdbook().args(["supports", "html"]).assert().success();
+}
+
+#[test]
+fn supports_after_global_flag() {
+    eelco()
+        .args(["-v", "mdbook", "supports", "html"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn sources_with_mdbook() {
+    eelco()
+        .args(["README.md", "mdbook"])
+        .assert()
+        .failure()
+        .stderr("Error: `eelco mdbook` tests the chapters of a book rather than \"README.md\"\n");
+}
+
+#[test]
+fn pass() {
+    let content = indoc! {"
+        # Chapter
+
+        ```nix skip
+        assert false; null
+        ```
+
+        ```nix
+        assert (1 + 1) == 2; null
+        ```
+    "};
+
+    let assert = eelco_mdbook()
+        .write_stdin(preprocessor_input(false, content))
+        .assert()
+        .success()
+        .stderr("PASS: /book/src/chapter.md:7\n");
+
+    assert_eq!(output_content(&assert.get_output().stdout), content);
+}
+
+#[test]
+fn strip() {
+    let assert = eelco_mdbook()
+        .write_stdin(preprocessor_input(
+            true,
+            indoc! {"
+                ```nix skip
+                assert false; null
+                ```
+
+                ```nix
+                null
+                ```
+            "},
+        ))
+        .assert()
+        .success();
+
+    assert_eq!(
+        output_content(&assert.get_output().stdout),
+        indoc! {"
+            ```nix
+            assert false; null
+            ```
+
+            ```nix
+            null
+            ```
+        "}
+    );
+}
+
+#[test]
+fn fail() {
+    eelco_mdbook()
+        .write_stdin(preprocessor_input(
+            false,
+            indoc! {"
+                ```nix
+                assert false; null
+                ```
+            "},
+        ))
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicates::str::contains("/book/src/chapter.md:1"));
+}
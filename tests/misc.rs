@@ -1,9 +1,9 @@
 mod util;
 
 use assert_fs::fixture::FileWriteStr;
-use indoc::indoc;
+use indoc::{formatdoc, indoc};
 use predicates::boolean::PredicateBooleanExt;
-use util::with_eelco;
+use util::{eelco, with_eelco};
 
 #[test]
 fn empty_file() {
@@ -66,3 +66,242 @@ fn skip() {
         eelco.assert().success();
     })
 }
+
+#[test]
+fn source_order() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix-repl
+            nix-repl> builtins.length (builtins.genList (x: x) 100000)
+            100000
+
+            ```
+
+            ```nix
+            null
+            ```
+
+            ```nix
+            0
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:1
+            PASS: {file_path}:7
+            PASS: {file_path}:11
+        "});
+    });
+}
+
+#[test]
+fn stdin() {
+    eelco()
+        .args(["--stdin-filename", "docs/stdin.md", "-"])
+        .write_stdin(indoc! {"
+            ```nix
+            null
+            ```
+        "})
+        .assert()
+        .success()
+        .stderr("PASS: docs/stdin.md:1\n");
+}
+
+#[test]
+fn frontmatter() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ---
+            eelco:
+              skip: true
+            ---
+
+            ```nix
+            assert false; null
+            ```
+        "})
+            .unwrap();
+
+        eelco
+            .assert()
+            .failure()
+            .stderr("Error: could not find any examples\n");
+    });
+}
+
+#[test]
+fn directive() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {r#"
+            <!-- eelco: skip reason="needs network" -->
+            ```nix
+            assert false; null
+            ```
+
+            ```nix
+            null
+            ```
+        "#})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:6\n"));
+    });
+}
+
+#[test]
+fn directive_timeout() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            <!-- eelco: timeout=1s -->
+            ```nix
+            let sum = n: builtins.foldl' builtins.add 0 (builtins.genList (x: x) n); in
+            builtins.foldl' (acc: _: acc + sum 100000) 0 (builtins.genList (x: x) 100000)
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .failure()
+            .stderr(format!("Error: {file_path}:2\n\ntimed out after 1s\n"));
+    });
+}
+
+#[test]
+fn source() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix source=lib.nix#L2
+            x = 1;
+            ```
+
+            ```nix source=lib.nix#strings
+            y = 2;
+            ```
+        "})
+            .unwrap();
+
+        let base_dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(
+            base_dir.path().join("lib.nix"),
+            indoc! {"
+                {
+                x = 1;
+                # region strings
+                y = \"2\";
+                # endregion
+                }
+            "},
+        )
+        .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--base-dir")
+            .arg(base_dir.path())
+            .assert()
+            .failure()
+            .stderr(formatdoc! {r#"
+                PASS: {file_path}:1
+                Error: {file_path}:5
+
+                --- {file_path}:5
+                +++ lib.nix#strings
+                @@ -1 +1 @@
+                -y = 2;
+                +y = "2";
+
+            "#});
+    });
+}
+
+#[test]
+fn source_skip() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            <!-- eelco: skip -->
+            ```nix source=missing.nix
+            old
+            ```
+
+            ```nix source=lib.nix
+            x = 1;
+            ```
+        "})
+            .unwrap();
+
+        let base_dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(base_dir.path().join("lib.nix"), "x = 1;\n").unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--base-dir")
+            .arg(base_dir.path())
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:6\n"));
+    });
+}
+
+#[test]
+fn update_sources() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix source=lib.nix
+            old
+            ```
+        "})
+            .unwrap();
+
+        let base_dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(base_dir.path().join("lib.nix"), "new\n").unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--base-dir")
+            .arg(base_dir.path())
+            .arg("--update-sources")
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+
+        assert_eq!(
+            std::fs::read_to_string(file.path()).unwrap(),
+            indoc! {"
+                ```nix source=lib.nix
+                new
+                ```
+            "}
+        );
+    });
+}
+
+#[test]
+fn update_sources_stdin() {
+    eelco()
+        .args(["--update-sources", "-"])
+        .write_stdin(indoc! {"
+            ```nix source=lib.nix
+            null
+            ```
+        "})
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--update-sources` cannot rewrite a document read from stdin",
+        ));
+}
@@ -144,3 +144,160 @@ fn multiline_result() {
             .stderr(format!("PASS: {file_path}:1\n"));
     });
 }
+
+#[test]
+fn prelude() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix prelude
+            # a comment
+            n = 2;
+            m = n + 1;
+            ```
+
+            ```nix-repl
+            #~ nix-repl> o = m + 1
+            #~
+            nix-repl> o
+            4
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:7\n"));
+    });
+}
+
+#[test]
+fn session() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix-repl session=tutorial
+            nix-repl> a = 1
+
+            ```
+
+            ```nix-repl
+            nix-repl> a = 2
+
+            ```
+
+            ```nix-repl session=tutorial
+            nix-repl> a + 1
+            2
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:1
+            PASS: {file_path}:6
+            PASS: {file_path}:11
+        "});
+    });
+}
+
+#[test]
+fn session_failure() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix-repl session=tutorial
+            nix-repl> a = 1
+
+            ```
+
+            ```nix-repl session=tutorial
+            nix-repl> a
+            2
+
+            ```
+
+            ```nix-repl session=tutorial
+            nix-repl> a
+            1
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().failure().stderr(formatdoc! {"
+            PASS: {file_path}:1
+            Error: {file_path}:6
+
+            Actual:
+
+            ```
+            1
+            ```
+
+            Expected:
+
+            ```
+            2
+            ```
+
+            {file_path}:11
+
+            not run because {file_path}:6 of the same session failed
+        "});
+    });
+}
+
+#[test]
+fn hermetic() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix-repl
+            nix-repl> 1 + 1
+            2
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("--hermetic")
+            .assert()
+            .success()
+            .stderr(format!("PASS: {file_path}:1\n"));
+    });
+}
+
+#[test]
+fn timeout() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ---
+            eelco:
+              timeout: 1s
+            ---
+
+            ```nix-repl
+            nix-repl> builtins.foldl' (acc: _: acc + builtins.length (builtins.genList (x: x) 100000)) 0 (builtins.genList (x: x) 100000)
+            0
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .assert()
+            .failure()
+            .stderr(format!("Error: {file_path}:6\n\ntimed out after 1s\n"));
+    });
+}
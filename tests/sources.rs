@@ -0,0 +1,145 @@
+mod util;
+
+use assert_fs::fixture::FileWriteStr;
+use indoc::{formatdoc, indoc};
+use util::with_eelco_file;
+
+#[test]
+fn nix_doc_comments() {
+    with_eelco_file("lib.nix", |file, eelco| {
+        file.write_str(indoc! {"
+            {
+              /**
+                Adds one.
+
+                # Example
+
+                ```nix
+                assert (1 + 1) == 2; null
+                ```
+
+                ```nix-repl
+                nix-repl> 1 + 1
+                2
+
+                ```
+              */
+              inc = x: x + 1;
+            }
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:7
+            PASS: {file_path}:11
+        "});
+    });
+}
+
+#[test]
+fn rst() {
+    with_eelco_file("doc.rst", |file, eelco| {
+        file.write_str(indoc! {"
+            Title
+            =====
+
+            .. code-block:: nix
+
+               assert (1 + 1) == 2; null
+
+            .. code-block:: nix
+               :skip:
+
+               assert false; null
+
+            .. code-block:: nix-repl
+
+               nix-repl> 1 + 1
+               2
+
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:4
+            PASS: {file_path}:13
+        "});
+    });
+}
+
+#[test]
+fn asciidoc() {
+    with_eelco_file("doc.adoc", |file, eelco| {
+        file.write_str(indoc! {"
+            = Title
+
+            [source,nix]
+            ----
+            assert (1 + 1) == 2; null
+            ----
+
+            [source,nix,skip]
+            ----
+            assert false; null
+            ----
+
+            [source,nix-repl]
+            ----
+            nix-repl> 1 + 1
+            2
+
+            ----
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:3
+            PASS: {file_path}:13
+        "});
+    });
+}
+
+#[test]
+fn org() {
+    with_eelco_file("doc.org", |file, eelco| {
+        file.write_str(indoc! {"
+            * Title
+
+            #+begin_src nix
+            assert (1 + 1) == 2; null
+            #+end_src
+
+            #+begin_src nix :eval no
+            assert false; null
+            #+end_src
+
+            #+BEGIN_SRC nix-repl
+            nix-repl> 1 + 1
+            2
+
+            #+END_SRC
+
+            #+begin_src nix
+            1 + 1
+            #+end_src
+
+            #+RESULTS:
+            : 2
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco.assert().success().stderr(formatdoc! {"
+            PASS: {file_path}:3
+            PASS: {file_path}:11
+            PASS: {file_path}:17
+        "});
+    });
+}
@@ -1,8 +1,27 @@
 use assert_fs::NamedTempFile;
 
-pub fn with_eelco(f: impl FnOnce(&mut NamedTempFile, &mut assert_cmd::Command)) {
-    let mut tmpfile = NamedTempFile::new("we-dont-particularly-mind.md").unwrap();
+/// `eelco`, unaffected by running in GitHub Actions.
+#[allow(dead_code)]
+pub fn eelco() -> assert_cmd::Command {
     let mut command = assert_cmd::Command::cargo_bin("eelco").unwrap();
+    command
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("GITHUB_STEP_SUMMARY");
+    command
+}
+
+#[allow(dead_code)]
+pub fn with_eelco(f: impl FnOnce(&mut NamedTempFile, &mut assert_cmd::Command)) {
+    with_eelco_file("we-dont-particularly-mind.md", f);
+}
+
+#[allow(dead_code)]
+pub fn with_eelco_file(
+    file_name: &str,
+    f: impl FnOnce(&mut NamedTempFile, &mut assert_cmd::Command),
+) {
+    let mut tmpfile = NamedTempFile::new(file_name).unwrap();
+    let mut command = eelco();
 
     command.arg(tmpfile.as_os_str());
     f(&mut tmpfile, &mut command);
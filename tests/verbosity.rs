@@ -0,0 +1,76 @@
+mod util;
+
+use assert_fs::fixture::FileWriteStr;
+use indoc::indoc;
+use predicates::{
+    prelude::PredicateBooleanExt,
+    str::{contains, is_match},
+};
+use util::with_eelco;
+
+#[test]
+fn quiet() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            null
+            ```
+
+            ```nix skip
+            null
+            ```
+        "})
+            .unwrap();
+
+        eelco
+            .arg("--quiet")
+            .assert()
+            .success()
+            .stderr(is_match("^1 passed, 0 failed, 1 skipped in [0-9]+ ms\n$").unwrap());
+    });
+}
+
+#[test]
+fn verbose() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix-repl
+            nix-repl> 1 + 1
+            2
+
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+        let dir = file.path().parent().unwrap().to_str().unwrap();
+
+        eelco.arg("-v").assert().success().stderr(
+            contains(format!("{file_path}:1: cd \"{dir}\" && \""))
+                .and(contains("\" \"repl\" \"--quiet\""))
+                .and(contains(format!("{file_path}:1: nix-repl> 1 + 1\n")))
+                .and(contains(format!("PASS: {file_path}:1\n")))
+                .and(contains("1 passed, 0 failed, 0 skipped")),
+        );
+    });
+}
+
+#[test]
+fn very_verbose() {
+    with_eelco(|file, eelco| {
+        file.write_str(indoc! {"
+            ```nix
+            { a = 1; }.a
+            ```
+        "})
+            .unwrap();
+
+        let file_path = file.path().to_str().unwrap();
+
+        eelco
+            .arg("-vv")
+            .assert()
+            .success()
+            .stderr(contains(format!("{file_path}:1: \"1\\n\"\n")));
+    });
+}